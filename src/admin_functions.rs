@@ -2,63 +2,193 @@ use near_sdk::require;
 
 use crate::*;
 
+//Roles del marketplace, el owner tiene todos y Admin incluye a los demás
+//Marketplace roles, the owner has all of them and Admin includes the others
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    Admin,
+    //Eduforms, su moderación, quizzes y drops
+    //Eduforms, their moderation, quizzes and drops
+    FormModerator,
+    //Registro de colecciones y allowlists
+    //Collection registry and allowlists
+    CollectionCurator,
+    //Comisiones y fondos del marketplace
+    //Marketplace fees and funds
+    FeeManager,
+    //Pausa del marketplace por partes
+    //Marketplace pause by scope
+    Pauser,
+    //Despliegue de código nuevo con upgrade
+    //Deployment of new code with upgrade
+    Upgrader,
+}
+
+//Owner propuesto, debe aceptar antes de expires_at
+//Proposed owner, it must accept before expires_at
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipProposal {
+    pub account_id: AccountId,
+    pub expires_at: Option<U64>,
+}
+
+const ROLES: [Role; 6] = [
+    Role::Admin,
+    Role::FormModerator,
+    Role::CollectionCurator,
+    Role::FeeManager,
+    Role::Pauser,
+    Role::Upgrader,
+];
+
 #[near_bindgen]
 impl Contract {
+    pub fn view_owner(&self) -> AccountId {
+        self.owner.clone()
+    }
 
-    pub fn change_admin_can_add_admins(&mut self) -> bool {
-        require!(env::signer_account_id() == self.owner, "Only the owner can change this");
-        self.admin_can_add_admins = !self.admin_can_add_admins;
-        self.admin_can_add_admins
+    pub fn is_owner(&self, owner: AccountId) -> bool {
+        self.owner == owner
     }
 
-    pub fn view_admin_can_add_admins(&self) -> bool {
-        self.admin_can_add_admins
+    //Abre una propuesta TransferOwnership, devuelve su id
+    //Open a TransferOwnership proposal, return its id
+    #[payable]
+    pub fn propose_owner(&mut self, new_owner: AccountId, expires_in: Option<U64>) -> U64 {
+        U64(self.propose(ProposalAction::TransferOwnership { new_owner, expires_in }))
     }
 
-    pub fn view_owner(&self) -> AccountId {
-        self.owner.clone()
+    //La cuenta propuesta acepta y pasa a ser el owner
+    //The proposed account accepts and becomes the owner
+    pub fn accept_ownership(&mut self) {
+        let proposal = self.ownership_proposal.take().expect("There is no ownership proposal");
+        require!(proposal.account_id == env::predecessor_account_id(), "Only the proposed account can accept the ownership");
+        if let Some(expires_at) = proposal.expires_at {
+            require!(env::block_timestamp() < expires_at.0, "The ownership proposal has expired");
+        }
+        log_event("evie", "1.0.0", "ownership_accepted", near_sdk::serde_json::json!({
+            "old_owner_id": self.owner,
+            "new_owner_id": proposal.account_id,
+        }));
+        self.owner = proposal.account_id;
     }
 
-    pub fn view_admins(&self) -> Vec<AccountId> {
-        self.admins.as_vector().clone().to_vec()
+    pub fn cancel_ownership_proposal(&mut self) {
+        require!(self.owner == env::predecessor_account_id(), "Only the owner can cancel the ownership proposal");
+        let proposal = self.ownership_proposal.take().expect("There is no ownership proposal");
+        log_event("evie", "1.0.0", "ownership_proposal_cancelled", near_sdk::serde_json::json!({
+            "owner_id": self.owner,
+            "proposed_owner_id": proposal.account_id,
+        }));
     }
 
-    pub fn add_admin(&mut self, admin: AccountId) {
-        require!(!self.admins.contains(&admin), "Admin is already an admin");
-        require!(self.owner == env::signer_account_id(), "Only the owner can add admins");
-        self.admins.insert(&admin);
+    pub fn get_ownership_proposal(&self) -> Option<OwnershipProposal> {
+        self.ownership_proposal.clone()
     }
 
-    pub fn remove_admin(&mut self, admin: AccountId) {
-        require!(self.admins.contains(&admin), "Admin is not an admin");
-        require!(self.owner == env::signer_account_id(), "Only the owner can remove admins");
-        self.admins.remove(&admin);
+    //Los admins y el owner otorgan todos los roles menos Admin, que solo cambia con propuestas GrantAdmin y RevokeAdmin
+    //The admins and the owner grant every role but Admin, which only changes through GrantAdmin and RevokeAdmin proposals
+    //Mientras haya menos admins que el threshold ninguna propuesta puede aprobarse, así que el owner otorga Admin directamente
+    //While there are fewer admins than the threshold no proposal can be approved, so the owner grants Admin directly
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        if role == Role::Admin {
+            require!(self.owner == env::predecessor_account_id(), "The Admin role is granted through proposals");
+            require!(
+                self.internal_admin_count() < u64::from(self.proposal_config.threshold),
+                "The Admin role is granted through proposals once there are threshold admins"
+            );
+        } else {
+            self.internal_assert_can_manage_role(role);
+        }
+        self.internal_grant_role(role, account_id);
     }
 
-    pub fn is_admin(&self, admin: AccountId) -> bool {
-        self.admins.contains(&admin)
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.internal_assert_can_manage_role(role);
+        self.internal_revoke_role(role, account_id);
     }
 
-    pub fn is_owner(&self, owner: AccountId) -> bool {
-        self.owner == owner
+    //Rol efectivo: el owner tiene todos y los admins todos menos Upgrader
+    //Effective role: the owner has all of them and the admins all but Upgrader
+    pub fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        self.owner == account_id
+            || (role != Role::Upgrader && self.internal_is_role_member(Role::Admin, &account_id))
+            || self.internal_is_role_member(role, &account_id)
     }
-    
-    pub fn add_admins(&mut self, admins: Vec<AccountId>) {
-        require!(self.owner == env::signer_account_id(), "Only the owner can add admins");
-        for admin in admins {
-            self.add_admin(admin);
-        }
+
+    //Roles otorgados directamente a la cuenta
+    //Roles granted directly to the account
+    pub fn get_account_roles(&self, account_id: AccountId) -> Vec<Role> {
+        ROLES.iter()
+            .filter(|role| self.internal_is_role_member(**role, &account_id))
+            .copied()
+            .collect()
     }
 
-    pub fn remove_admins(&mut self, admins: Vec<AccountId>) {
-        require!(self.owner == env::signer_account_id(), "Only the owner can remove admins");
-        for admin in admins {
-            self.remove_admin(admin);
+    pub fn get_role_members(&self, role: Role, from_index: Option<U128>, limit: Option<u64>) -> Vec<AccountId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        match self.role_members.get(&role) {
+            Some(members) => members.iter()
+                .skip(start as usize)
+                .take(limit.unwrap_or(50) as usize)
+                .collect(),
+            None => vec![],
         }
     }
+}
+
+impl Contract {
+    pub fn internal_is_role_member(&self, role: Role, account_id: &AccountId) -> bool {
+        self.role_members
+            .get(&role)
+            .map(|members| members.contains(account_id))
+            .unwrap_or(false)
+    }
+
+    //Se autoriza con el predecessor, así una DAO puede ser owner o admin ejecutando sus propuestas
+    //Authorization uses the predecessor, so a DAO can be owner or admin by executing its proposals
+    pub fn internal_assert_can_manage_role(&self, role: Role) {
+        require!(role != Role::Admin, "The Admin role is granted and revoked through proposals");
+        require!(
+            self.has_role(Role::Admin, env::predecessor_account_id()),
+            "Only admins or the owner can grant or revoke roles"
+        );
+    }
+
+    pub fn internal_admin_count(&self) -> u64 {
+        self.role_members.get(&Role::Admin).map(|members| members.len()).unwrap_or(0)
+    }
+
+    pub fn internal_grant_role(&mut self, role: Role, account_id: AccountId) {
+        let mut members = self.role_members.get(&role).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::RoleMembersInner { role }.try_to_vec().unwrap())
+        });
+        require!(members.insert(&account_id), "The account already has this role");
+        self.role_members.insert(&role, &members);
+    }
+
+    pub fn internal_revoke_role(&mut self, role: Role, account_id: AccountId) {
+        let mut members = self.role_members.get(&role).expect("The account doesn't have this role");
+        require!(members.remove(&account_id), "The account doesn't have this role");
+        self.role_members.insert(&role, &members);
+    }
 
-    pub fn change_owner(&mut self, new_owner: AccountId) {
-        require!(self.owner == env::signer_account_id(), "Only the owner can change the owner");
-        self.owner = new_owner;
+    //Primer paso del cambio de owner, se ejecuta con una propuesta TransferOwnership, expires_in en nanosegundos
+    //First step of the owner change, it runs through a TransferOwnership proposal, expires_in in nanoseconds
+    pub fn internal_propose_owner(&mut self, new_owner: AccountId, expires_in: Option<U64>) {
+        require!(new_owner != self.owner, "The account is already the owner");
+        let proposal = OwnershipProposal {
+            account_id: new_owner,
+            expires_at: expires_in.map(|expires_in| U64(env::block_timestamp() + expires_in.0)),
+        };
+        log_event("evie", "1.0.0", "ownership_proposed", near_sdk::serde_json::json!({
+            "owner_id": self.owner,
+            "proposed_owner_id": proposal.account_id,
+            "expires_at": proposal.expires_at,
+        }));
+        self.ownership_proposal = Some(proposal);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,207 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Tipo de allowlist: un set explicito de cuentas o la raíz de un Merkle tree
+//Allowlist kind: an explicit set of accounts or the root of a Merkle tree
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AllowlistKind {
+    Accounts,
+    //Hojas = sha256(account ID), cada nivel = sha256(menor + mayor)
+    //Leaves = sha256(account ID), each level = sha256(smaller + greater)
+    MerkleRoot { root: Base64VecU8 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Allowlist {
+    pub owner_id: AccountId,
+    pub kind: AllowlistKind,
+    //Las allowlists de curadores las paga el pool, las de vendedores su deposito adjunto
+    //Curator allowlists are paid by the pool, seller allowlists by their attached deposit
+    pub paid_by_pool: bool,
+    pub storage_used: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    //Registra una allowlist, sin merkle_root es un set de cuentas (add_to_allowlist)
+    //Register an allowlist, without merkle_root it is a set of accounts (add_to_allowlist)
+    #[payable]
+    pub fn create_allowlist(&mut self, merkle_root: Option<Base64VecU8>) -> AllowlistId {
+        let kind = match merkle_root {
+            Some(root) => {
+                require!(root.0.len() == 32, "merkle_root must be a sha256 hash");
+                AllowlistKind::MerkleRoot { root }
+            }
+            None => AllowlistKind::Accounts,
+        };
+        let allowlist_id = self.next_allowlist_id;
+        self.next_allowlist_id += 1;
+
+        let initial_storage_usage = env::storage_usage();
+        let mut allowlist = Allowlist {
+            owner_id: env::predecessor_account_id(),
+            kind,
+            paid_by_pool: self.has_role(Role::CollectionCurator, env::predecessor_account_id()),
+            storage_used: 0,
+        };
+        self.allowlists.insert(&allowlist_id, &allowlist);
+        self.internal_charge_allowlist_storage(allowlist_id, &mut allowlist, initial_storage_usage);
+        allowlist_id
+    }
+
+    #[payable]
+    pub fn add_to_allowlist(&mut self, allowlist_id: AllowlistId, account_ids: Vec<AccountId>) {
+        let mut allowlist = self.internal_allowlist_for_edit(allowlist_id);
+        require!(allowlist.kind == AllowlistKind::Accounts, "Merkle allowlists are updated with set_allowlist_merkle_root");
+
+        let initial_storage_usage = env::storage_usage();
+        let mut accounts = self.allowlist_accounts.get(&allowlist_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::AllowlistAccountsInner { allowlist_id }.try_to_vec().unwrap())
+        });
+        for account_id in account_ids {
+            accounts.insert(&account_id);
+        }
+        self.allowlist_accounts.insert(&allowlist_id, &accounts);
+        self.internal_charge_allowlist_storage(allowlist_id, &mut allowlist, initial_storage_usage);
+    }
+
+    pub fn remove_from_allowlist(&mut self, allowlist_id: AllowlistId, account_ids: Vec<AccountId>) {
+        let mut allowlist = self.internal_allowlist_for_edit(allowlist_id);
+
+        let initial_storage_usage = env::storage_usage();
+        let mut accounts = self.allowlist_accounts.get(&allowlist_id).expect("The allowlist has no accounts");
+        for account_id in account_ids {
+            accounts.remove(&account_id);
+        }
+        self.allowlist_accounts.insert(&allowlist_id, &accounts);
+        self.internal_charge_allowlist_storage(allowlist_id, &mut allowlist, initial_storage_usage);
+    }
+
+    pub fn set_allowlist_merkle_root(&mut self, allowlist_id: AllowlistId, merkle_root: Base64VecU8) {
+        let mut allowlist = self.internal_allowlist_for_edit(allowlist_id);
+        require!(allowlist.kind != AllowlistKind::Accounts, "Account allowlists are updated with add_to_allowlist");
+        require!(merkle_root.0.len() == 32, "merkle_root must be a sha256 hash");
+        allowlist.kind = AllowlistKind::MerkleRoot { root: merkle_root };
+        self.allowlists.insert(&allowlist_id, &allowlist);
+    }
+
+    //Borra la allowlist y devuelve su storage, las ventas y drops que la usen quedan cerrados
+    //Delete the allowlist and return its storage, the sales and drops using it stay closed
+    pub fn remove_allowlist(&mut self, allowlist_id: AllowlistId) {
+        let mut allowlist = self.internal_allowlist_for_edit(allowlist_id);
+
+        let initial_storage_usage = env::storage_usage();
+        if let Some(mut accounts) = self.allowlist_accounts.remove(&allowlist_id) {
+            accounts.clear();
+        }
+        self.allowlists.remove(&allowlist_id);
+        self.internal_charge_allowlist_storage(allowlist_id, &mut allowlist, initial_storage_usage);
+    }
+
+    pub fn get_allowlist(&self, allowlist_id: AllowlistId) -> Option<Allowlist> {
+        self.allowlists.get(&allowlist_id)
+    }
+
+    pub fn get_allowlist_accounts(&self, allowlist_id: AllowlistId, from_index: Option<U128>, limit: Option<u64>) -> Vec<AccountId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        match self.allowlist_accounts.get(&allowlist_id) {
+            Some(accounts) => accounts.iter()
+                .skip(start as usize)
+                .take(limit.unwrap_or(50) as usize)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    //proof son los hashes hermanos desde la hoja hasta la raíz (solo allowlists Merkle)
+    //proof are the sibling hashes from the leaf to the root (Merkle allowlists only)
+    pub fn is_allowlisted(&self, allowlist_id: AllowlistId, account_id: AccountId, proof: Option<Vec<Base64VecU8>>) -> bool {
+        let allowlist = if let Some(allowlist) = self.allowlists.get(&allowlist_id) {
+            allowlist
+        } else {
+            return false;
+        };
+
+        match allowlist.kind {
+            AllowlistKind::Accounts => self.allowlist_accounts
+                .get(&allowlist_id)
+                .map(|accounts| accounts.contains(&account_id))
+                .unwrap_or(false),
+            AllowlistKind::MerkleRoot { root } => {
+                let mut hash = env::sha256(account_id.as_bytes());
+                for sibling in proof.unwrap_or_default() {
+                    hash = if hash <= sibling.0 {
+                        env::sha256(&[hash, sibling.0].concat())
+                    } else {
+                        env::sha256(&[sibling.0, hash].concat())
+                    };
+                }
+                hash == root.0
+            }
+        }
+    }
+}
+
+impl Contract {
+    pub fn internal_assert_allowlisted(&self, allowlist_id: AllowlistId, account_id: &AccountId, proof: Option<Vec<Base64VecU8>>) {
+        require!(
+            self.is_allowlisted(allowlist_id, account_id.clone(), proof),
+            format!("{} is not in allowlist {}", account_id, allowlist_id)
+        );
+    }
+
+    //Solo el creador de la allowlist o los curadores de colecciones pueden editarla
+    //Only the creator of the allowlist or the collection curators can edit it
+    pub fn internal_allowlist_for_edit(&self, allowlist_id: AllowlistId) -> Allowlist {
+        let allowlist = self.allowlists.get(&allowlist_id).expect("Allowlist not found");
+        require!(
+            allowlist.owner_id == env::predecessor_account_id()
+                || self.has_role(Role::CollectionCurator, env::predecessor_account_id()),
+            "Only the creator or collection curators can edit an allowlist"
+        );
+        allowlist
+    }
+
+    //Cobra el storage usado desde initial_storage_usage al pool o al deposito adjunto, el exceso se devuelve
+    //Charge the storage used since initial_storage_usage to the pool or to the attached deposit, the excess is refunded
+    pub fn internal_charge_allowlist_storage(&mut self, allowlist_id: AllowlistId, allowlist: &mut Allowlist, initial_storage_usage: u64) {
+        let storage_usage = env::storage_usage();
+        let mut refund = env::attached_deposit();
+
+        if storage_usage >= initial_storage_usage {
+            let bytes = storage_usage - initial_storage_usage;
+            if allowlist.paid_by_pool {
+                self.internal_charge_pool_storage(initial_storage_usage);
+            } else {
+                let cost = Balance::from(bytes) * STORAGE_PRICE_PER_BYTE;
+                require!(refund >= cost, format!("Attach at least {} yoctoNEAR for the allowlist storage", cost));
+                refund -= cost;
+            }
+            allowlist.storage_used += bytes;
+        } else {
+            //El storage liberado vuelve a quien lo pagó
+            //The freed storage goes back to whoever paid it
+            let bytes = (initial_storage_usage - storage_usage).min(allowlist.storage_used);
+            let cost = Balance::from(bytes) * STORAGE_PRICE_PER_BYTE;
+            if allowlist.paid_by_pool {
+                self.form_storage_pool += cost;
+            } else if cost > 0 {
+                Promise::new(allowlist.owner_id.clone()).transfer(cost);
+            }
+            allowlist.storage_used -= bytes;
+        }
+
+        //Una allowlist borrada no se vuelve a guardar
+        //A removed allowlist is not stored again
+        if self.allowlists.get(&allowlist_id).is_some() {
+            self.allowlists.insert(&allowlist_id, allowlist);
+        }
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+}
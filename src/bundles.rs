@@ -0,0 +1,435 @@
+use near_sdk::{require, PromiseResult};
+
+use crate::*;
+
+//Lote de ventas del mismo owner que se compran juntas por un solo precio
+//Lot of sales of the same owner that are bought together for a single price
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Bundle {
+    pub owner_id: AccountId,
+    //IDs de las ventas (`contract + DELIMETER + token ID`), pueden ser de distintos contratos
+    //IDs of the sales (`contract + DELIMETER + token ID`), they can be from different contracts
+    pub sale_ids: Vec<ContractAndTokenId>,
+    pub price: SalePriceInYoctoNear,
+    //Deposito adjunto para el storage del bundle, se devuelve al owner al venderlo o borrarlo
+    //Deposit attached for the bundle storage, it is returned to the owner when sold or removed
+    pub storage_deposit: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleWithId {
+    pub id: BundleId,
+    pub bundle: Bundle,
+}
+
+//Transferencia de un token del bundle y la parte del pago que le corresponde
+//Transfer of one token of the bundle and the share of the payment it gets
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BundleLeg {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    //Owner de la venta, si el bundle no se completa el token vuelve a él
+    //Owner of the sale, if the bundle doesn't settle the token goes back to them
+    pub owner_id: AccountId,
+    pub share: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    //Agrupa ventas ya listadas en un bundle, el deposito cubre su storage y el exceso se devuelve
+    //Group already listed sales in a bundle, the deposit covers its storage and the excess is refunded
+    #[payable]
+    pub fn create_bundle(&mut self, sale_ids: Vec<ContractAndTokenId>, price: U128) -> BundleId {
+        self.internal_assert_not_paused(PauseScope::Listing);
+        let owner_id = env::predecessor_account_id();
+        require!(
+            sale_ids.len() >= 2 && sale_ids.len() <= MAX_BUNDLE_ITEMS,
+            format!("A bundle must have between 2 and {} sales", MAX_BUNDLE_ITEMS)
+        );
+        require!(price.0 > 0, "The bundle price must be greater than zero");
+
+        let initial_storage_usage = env::storage_usage();
+        let bundle_id = self.next_bundle_id;
+        self.next_bundle_id += 1;
+
+        for sale_id in &sale_ids {
+            let mut sale = self.sales.get(sale_id).expect("No sale found");
+            require!(sale.owner_id == owner_id, "Only the owner of the sales can bundle them");
+            require!(sale.bundle_id.is_none(), format!("{} is already in a bundle", sale_id));
+            require!(sale.pending_buyer_id.is_none(), format!("{} has a purchase in progress", sale_id));
+            require!(
+                sale.reserved_for.is_none() && sale.allowlist_id.is_none(),
+                "Private or allowlisted sales can't be bundled"
+            );
+            sale.bundle_id = Some(bundle_id);
+            self.sales.insert(sale_id, &sale);
+        }
+
+        let purchase_gas = self.internal_bundle_purchase_gas(&sale_ids);
+        require!(
+            purchase_gas <= MAX_BUNDLE_PURCHASE_GAS,
+            format!("Buying the bundle needs {} gas, more than the maximum {}", purchase_gas.0, MAX_BUNDLE_PURCHASE_GAS.0)
+        );
+
+        let mut bundle = Bundle { owner_id: owner_id.clone(), sale_ids, price, storage_deposit: U128(0) };
+        self.bundles.insert(&bundle_id, &bundle);
+
+        //Cobramos el storage del bundle al deposito adjunto
+        //Charge the bundle storage to the attached deposit
+        let cost = Balance::from(env::storage_usage() - initial_storage_usage) * STORAGE_PRICE_PER_BYTE;
+        let deposit = env::attached_deposit();
+        require!(deposit >= cost, format!("Attach at least {} yoctoNEAR for the bundle storage", cost));
+        bundle.storage_deposit = U128(cost);
+        self.bundles.insert(&bundle_id, &bundle);
+        if deposit > cost {
+            Promise::new(owner_id).transfer(deposit - cost);
+        }
+        bundle_id
+    }
+
+    //Deshace el bundle, las ventas siguen listadas por separado
+    //Undo the bundle, the sales stay listed separately
+    #[payable]
+    pub fn remove_bundle(&mut self, bundle_id: BundleId) {
+        assert_one_yocto();
+        let bundle = self.bundles.get(&bundle_id).expect("No bundle found");
+        assert_eq!(env::predecessor_account_id(), bundle.owner_id, "Only the owner can remove a bundle");
+        for sale_id in &bundle.sale_ids {
+            if let Some(sale) = self.sales.get(sale_id) {
+                assert!(sale.pending_buyer_id.is_none(), "This bundle has a purchase in progress");
+            }
+        }
+        self.internal_remove_bundle(bundle_id, true);
+    }
+
+    //Compra el bundle por su precio exacto, que se reparte entre los tokens en proporción a su precio de lista
+    //Buy the bundle at its exact price, which is split among the tokens in proportion to their list price
+    //max_price protege al comprador si el owner subió el precio, el exceso del deposito se devuelve
+    //max_price protects the buyer if the owner raised the price, the deposit surplus is refunded
+    //Las ventas se bloquean y todos los tokens pasan primero al escrow, así la compra es todo o nada
+    //The sales are locked and every token first moves to the escrow, so the purchase is all or nothing
+    #[payable]
+    pub fn offer_bundle(&mut self, bundle_id: BundleId, max_price: Option<U128>) -> Promise {
+        self.internal_assert_not_paused(PauseScope::Purchases);
+        let deposit = env::attached_deposit();
+        let bundle = self.bundles.get(&bundle_id).expect("No bundle found");
+        let buyer_id = env::predecessor_account_id();
+        assert_ne!(buyer_id, bundle.owner_id, "I catch you, you can't offer on your own bundle.");
+        assert!(
+            deposit >= bundle.price.0,
+            "Deposit must be greater than or equal to the bundle price: {:?}",
+            bundle.price.0
+        );
+        if let Some(max_price) = max_price {
+            assert!(
+                bundle.price.0 <= max_price.0,
+                "The bundle price {} is higher than max_price {}",
+                bundle.price.0, max_price.0
+            );
+        }
+        //Los overrides de gas pueden haber cambiado desde create_bundle
+        //The gas overrides may have changed since create_bundle
+        let purchase_gas = self.internal_bundle_purchase_gas(&bundle.sale_ids);
+        require!(
+            purchase_gas <= MAX_BUNDLE_PURCHASE_GAS,
+            format!("Buying the bundle needs {} gas, more than the maximum {}", purchase_gas.0, MAX_BUNDLE_PURCHASE_GAS.0)
+        );
+        let price = bundle.price.0;
+        if deposit > price {
+            Promise::new(buyer_id.clone()).transfer(deposit - price);
+        }
+
+        //Bloqueamos cada venta hasta resolve_bundle_purchase, como en offer
+        //Lock every sale until resolve_bundle_purchase, as in offer
+        let sales: Vec<Sale> = bundle.sale_ids.iter()
+            .map(|sale_id| {
+                let mut sale = self.sales.get(sale_id).expect("No sale found");
+                assert!(
+                    sale.owner_id == bundle.owner_id && sale.bundle_id == Some(bundle_id),
+                    "The sale is no longer part of this bundle"
+                );
+                assert!(sale.pending_buyer_id.is_none(), "This bundle has a purchase in progress");
+                sale.pending_buyer_id = Some(buyer_id.clone());
+                self.sales.insert(sale_id, &sale);
+                sale
+            })
+            .collect();
+
+        //Repartimos el pago según el precio de lista de cada venta, el ultimo token recibe el resto
+        //Split the payment by the list price of each sale, the last token gets the remainder
+        let total_weight = sales.iter()
+            .try_fold(0u128, |total, sale| total.checked_add(sale.sale_conditions.0))
+            .expect("The list prices of the bundle are too large to split its price");
+        let mut remainder = price;
+        let mut legs: Vec<BundleLeg> = Vec::new();
+        for (index, sale) in sales.iter().enumerate() {
+            //En puntos basicos para no desbordar u128, si todos los precios son 0 se reparte igual
+            //In basis points to avoid overflowing u128, if every price is 0 it is split equally
+            //Un precio de lista tan grande que no cabe en puntos basicos rechaza la compra
+            //A list price so large it doesn't fit in basis points rejects the purchase
+            let share = if index == sales.len() - 1 {
+                remainder
+            } else {
+                let weight = sale.sale_conditions.0.checked_mul(10_000)
+                    .expect("The list prices of the bundle are too large to split its price");
+                match weight.checked_div(total_weight) {
+                    Some(basis_points) => price / 10_000 * basis_points,
+                    None => price / sales.len() as u128,
+                }
+            };
+            remainder -= share;
+            legs.push(BundleLeg {
+                nft_contract_id: sale.nft_contract_id.parse().unwrap(),
+                token_id: sale.token_id.clone(),
+                owner_id: sale.owner_id.clone(),
+                share: U128(share),
+            });
+        }
+
+        //Una transferencia al escrow por token (con su payout), todas se resuelven juntas en resolve_bundle_purchase
+        //One transfer to the escrow per token (with its payout), all of them are resolved together in resolve_bundle_purchase
+        let escrow = sales.iter()
+            .zip(legs.iter())
+            .map(|(sale, leg)| {
+                ext_contract::ext(leg.nft_contract_id.clone())
+                    .with_attached_deposit(1)
+                    .with_static_gas(self.internal_gas_config(&leg.nft_contract_id).nft_transfer())
+                    .nft_transfer_payout(
+                        env::current_account_id(),
+                        leg.token_id.clone(),
+                        sale.approval_id,
+                        "payout from Evie Market".to_string(),
+                        leg.share,
+                        MAX_ROYALTIES_ACCOUNTS.into(),
+                    )
+            })
+            .reduce(|transfers, transfer| transfers.and(transfer))
+            .unwrap();
+
+        //El callback también entrega los tokens desde el escrow
+        //The callback also delivers the tokens from the escrow
+        let deliveries: Vec<EscrowTransfer> = legs.iter()
+            .map(|leg| EscrowTransfer {
+                nft_contract_id: leg.nft_contract_id.clone(),
+                token_id: leg.token_id.clone(),
+                receiver_id: buyer_id.clone(),
+            })
+            .collect();
+        escrow.then(ext_self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_BUNDLE_PURCHASE + self.internal_escrow_delivery_gas(deliveries.iter().map(|delivery| &delivery.nft_contract_id)))
+            .resolve_bundle_purchase(bundle_id, buyer_id, legs))
+    }
+
+    //Si todos los tokens llegaron al escrow con un payout válido se paga y se entregan al comprador,
+    //si no, se devuelve el pago completo y cada token que llegó vuelve al owner de su venta
+    //If every token reached the escrow with a valid payout they are paid and delivered to the buyer,
+    //otherwise the whole payment is refunded and each token that arrived goes back to the owner of its sale
+    //No hace panic para no perder el reembolso ni los tokens
+    //It doesn't panic so neither the refund nor the tokens are lost
+    #[private]
+    pub fn resolve_bundle_purchase(&mut self, bundle_id: BundleId, buyer_id: AccountId, legs: Vec<BundleLeg>) -> U128 {
+        let mut payouts: Vec<Option<HashMap<AccountId, U128>>> = Vec::new();
+        let mut escrowed: Vec<bool> = Vec::new();
+        for (index, leg) in legs.iter().enumerate() {
+            match env::promise_result(index as u64) {
+                PromiseResult::Successful(value) => {
+                    escrowed.push(true);
+                    payouts.push(self.internal_valid_payout(&value, leg.share.0).and_then(|payout| {
+                        self.internal_payout_within_policy(payout, leg.share.0, &leg.owner_id, &buyer_id, &leg.nft_contract_id, &leg.token_id)
+                    }));
+                }
+                _ => {
+                    env::log_str(&format!("Bundle escrow of {} failed", leg.token_id));
+                    escrowed.push(false);
+                    payouts.push(None);
+                }
+            }
+        }
+        let settled = payouts.iter().all(|payout| payout.is_some());
+
+        //Las ventas de los tokens que llegaron se quitan (el approval ya no sirve), las otras se desbloquean
+        //The sales of the tokens that arrived are removed (the approval is no longer valid), the others are unlocked
+        for (leg, escrowed) in legs.iter().zip(escrowed.iter()) {
+            let sale_id = format!("{}{}{}", leg.nft_contract_id, DELIMETER, leg.token_id);
+            if let Some(mut sale) = self.sales.get(&sale_id) {
+                if sale.pending_buyer_id.as_ref() == Some(&buyer_id) {
+                    if *escrowed {
+                        self.internal_try_remove_sale(&sale_id);
+                    } else {
+                        sale.pending_buyer_id = None;
+                        self.sales.insert(&sale_id, &sale);
+                    }
+                }
+            }
+        }
+        //El bundle sigue si ningún token se movió, si no se borra y su storage vuelve al owner
+        //The bundle stays if no token moved, otherwise it is deleted and its storage goes back to the owner
+        if escrowed.iter().any(|escrowed| *escrowed) && self.bundles.get(&bundle_id).is_some() {
+            self.internal_remove_bundle(bundle_id, true);
+        }
+
+        let refund = if settled {
+            for payout in payouts.into_iter().flatten() {
+                for (receiver_id, amount) in payout {
+                    Promise::new(receiver_id).transfer(amount.0);
+                }
+            }
+            0
+        } else {
+            let price: u128 = legs.iter().map(|leg| leg.share.0).sum();
+            env::log_str(&format!("Bundle {} purchase failed, refunding {}", bundle_id, price));
+            Promise::new(buyer_id.clone()).transfer(price);
+            price
+        };
+
+        let deliveries: Vec<EscrowTransfer> = legs.iter()
+            .zip(escrowed.iter())
+            .filter(|(_, escrowed)| **escrowed)
+            .map(|(leg, _)| EscrowTransfer {
+                nft_contract_id: leg.nft_contract_id.clone(),
+                token_id: leg.token_id.clone(),
+                receiver_id: if settled { buyer_id.clone() } else { leg.owner_id.clone() },
+            })
+            .collect();
+        self.internal_deliver_escrowed(deliveries, format!("Evie bundle {}", bundle_id));
+        U128(refund)
+    }
+
+    pub fn get_bundle(&self, bundle_id: BundleId) -> Option<Bundle> {
+        self.bundles.get(&bundle_id)
+    }
+
+    pub fn get_bundles(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<BundleWithId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.bundles.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(id, bundle)| BundleWithId { id, bundle })
+            .collect()
+    }
+}
+
+impl Contract {
+    //Borra el bundle y suelta sus ventas, con refund_storage devuelve el deposito del storage al owner
+    //Delete the bundle and release its sales, with refund_storage it returns the storage deposit to the owner
+    pub fn internal_remove_bundle(&mut self, bundle_id: BundleId, refund_storage: bool) {
+        let bundle = self.bundles.remove(&bundle_id).expect("No bundle found");
+        for sale_id in &bundle.sale_ids {
+            if let Some(mut sale) = self.sales.get(sale_id) {
+                sale.bundle_id = None;
+                self.sales.insert(sale_id, &sale);
+            }
+        }
+        if refund_storage && bundle.storage_deposit.0 > 0 {
+            Promise::new(bundle.owner_id).transfer(bundle.storage_deposit.0);
+        }
+    }
+
+    //Gas de la compra: cada nft_transfer_payout al escrow, resolve_bundle_purchase y las entregas desde el escrow
+    //Gas of the purchase: each nft_transfer_payout to the escrow, resolve_bundle_purchase and the deliveries from the escrow
+    pub fn internal_bundle_purchase_gas(&self, sale_ids: &[ContractAndTokenId]) -> Gas {
+        let nft_contract_ids: Vec<AccountId> = sale_ids.iter()
+            .map(|sale_id| self.sales.get(sale_id).expect("No sale found").nft_contract_id.parse().unwrap())
+            .collect();
+        let escrow_gas = nft_contract_ids.iter()
+            .map(|nft_contract_id| self.internal_gas_config(nft_contract_id).nft_transfer())
+            .fold(Gas(0), |total, gas| total + gas);
+        escrow_gas + GAS_FOR_RESOLVE_BUNDLE_PURCHASE + self.internal_escrow_delivery_gas(nft_contract_ids.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::PromiseResult;
+
+    use super::*;
+    use crate::testing::*;
+
+    const SALE: &str = r#"{"sale_conditions": "1000000000000000000000000"}"#;
+
+    fn sale_id(token_id: &str) -> ContractAndTokenId {
+        format!("{}{}{}", NFT, DELIMETER, token_id)
+    }
+
+    //alice lista los tokens 1 y 2 y los agrupa en el bundle 0
+    //alice lists the tokens 1 and 2 and groups them in the bundle 0
+    fn setup_bundle() -> Contract {
+        let mut contract = new_contract();
+        let alice = account("alice.near");
+        deposit_storage(&mut contract, &alice, 2);
+        approve(&mut contract, &alice, "1", 1, SALE);
+        approve(&mut contract, &alice, "2", 2, SALE);
+        set_context(context(&alice).attached_deposit(ONE_NEAR), vec![]);
+        contract.create_bundle(vec![sale_id("1"), sale_id("2")], U128(ONE_NEAR));
+        contract
+    }
+
+    fn leg(token_id: &str, owner_id: &str, share: u128) -> BundleLeg {
+        BundleLeg { nft_contract_id: nft(), token_id: token_id.to_string(), owner_id: account(owner_id), share: U128(share) }
+    }
+
+    fn payout(account_id: &str, amount: u128) -> PromiseResult {
+        success(&Payout { payout: HashMap::from([(account(account_id), U128(amount))]) })
+    }
+
+    #[test]
+    fn unsettled_bundle_returns_escrowed_token_to_its_owner() {
+        let mut contract = setup_bundle();
+        let buyer = account("buyer.near");
+        set_context(context(&buyer).attached_deposit(ONE_NEAR), vec![]);
+        contract.offer_bundle(0, None);
+        assert_eq!(contract.sales.get(&sale_id("2")).unwrap().pending_buyer_id, Some(buyer.clone()));
+
+        //El token 1 llegó al escrow, el token 2 no
+        //The token 1 reached the escrow, the token 2 didn't
+        set_callback_context(0, vec![payout("alice.near", ONE_NEAR / 2), PromiseResult::Failed]);
+        let refund = contract.resolve_bundle_purchase(0, buyer.clone(), vec![leg("1", "alice.near", ONE_NEAR / 2), leg("2", "alice.near", ONE_NEAR / 2)]);
+
+        assert_eq!(refund, U128(ONE_NEAR));
+        assert_eq!(transferred_to(&buyer), ONE_NEAR);
+        assert_eq!(nft_transfers(), vec![("1".to_string(), "alice.near".to_string())]);
+        assert_eq!(contract.get_escrowed_token(nft(), "1".to_string()).unwrap().receiver_id, account("alice.near"));
+        assert!(contract.sales.get(&sale_id("1")).is_none());
+        assert_eq!(contract.sales.get(&sale_id("2")).unwrap().pending_buyer_id, None);
+        assert!(contract.get_bundle(0).is_none());
+    }
+
+    #[test]
+    fn unsettled_legs_go_back_to_their_own_sale_owner() {
+        let mut contract = new_contract();
+        let buyer = account("buyer.near");
+        //El payout del token 2 no paga a bob, rompe la política y el bundle no se completa
+        //The payout of the token 2 doesn't pay bob, it breaks the policy and the bundle doesn't settle
+        set_callback_context(0, vec![payout("alice.near", 600), payout("carol.near", 400)]);
+        contract.resolve_bundle_purchase(0, buyer.clone(), vec![leg("1", "alice.near", 600), leg("2", "bob.near", 400)]);
+
+        assert_eq!(transferred_to(&buyer), 1000);
+        assert_eq!(transferred_to(&account("carol.near")), 0);
+        assert_eq!(
+            nft_transfers(),
+            vec![("1".to_string(), "alice.near".to_string()), ("2".to_string(), "bob.near".to_string())]
+        );
+    }
+
+    #[test]
+    fn relisting_by_a_new_owner_undoes_the_bundle() {
+        let mut contract = setup_bundle();
+        let bob = account("bob.near");
+        deposit_storage(&mut contract, &bob, 1);
+        //alice transfirió el token 2 a bob, que lo vuelve a listar
+        //alice transferred the token 2 to bob, who lists it again
+        approve(&mut contract, &bob, "2", 3, SALE);
+
+        assert!(contract.get_bundle(0).is_none());
+        let sale = contract.sales.get(&sale_id("2")).unwrap();
+        assert_eq!(sale.owner_id, bob);
+        assert_eq!(sale.bundle_id, None);
+        assert_eq!(contract.sales.get(&sale_id("1")).unwrap().bundle_id, None);
+        assert_eq!(contract.get_supply_by_owner_id(account("alice.near")), U64(1));
+        assert_eq!(contract.get_supply_by_owner_id(bob), U64(1));
+    }
+}
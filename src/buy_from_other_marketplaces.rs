@@ -12,6 +12,7 @@ impl BuyFromOtherMarketplaces for Contract {
 
     #[payable]
     fn buy_from_other_marketplaces(&mut self, user: AccountId, item: TokenId, price: U128) {
+        self.internal_assert_not_paused(PauseScope::Purchases);
         require!(env::attached_deposit() >= price.0 + ONE_NEAR, "No depositaste el precio + 1 NEAR");
         let cart: Vec<CartItem> = self.cart.get(&user).unwrap_or_default();
         
@@ -31,7 +32,7 @@ impl BuyFromOtherMarketplaces for Contract {
             //self.remove_item(user.clone(), buy_item.clone());
             ext_contract::ext(buy_item.contract_id.clone()).with_attached_deposit(
                 price.0 + ONE_NEAR,
-            ).with_static_gas(GAS_FOR_NFT_TRANSFER).nft_buy(buy_item.token_id.clone());
+            ).with_static_gas(self.internal_gas_config(&buy_item.contract_id).nft_transfer()).nft_buy(buy_item.token_id.clone());
             //Add callback for send the nft to the user
         }
         
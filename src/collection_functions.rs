@@ -0,0 +1,75 @@
+use near_sdk::require;
+
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_collection_mode(&mut self, mode: CollectionMode) {
+        require!(self.has_role(Role::CollectionCurator, env::predecessor_account_id()), "Only collection curators can change the collection mode");
+        self.collection_mode = mode;
+    }
+
+    pub fn get_collection_mode(&self) -> CollectionMode {
+        self.collection_mode
+    }
+
+    //Añade o actualiza una colección en el registro
+    //Add or update a collection in the registry
+    pub fn set_collection(&mut self, nft_contract_id: AccountId, collection: Collection) {
+        require!(self.has_role(Role::CollectionCurator, env::predecessor_account_id()), "Only collection curators can set collections");
+        if let Some(form_id) = collection.edu_form_id {
+            require!(self.edu_forms.get(&form_id).is_some(), "EduForm not found");
+        }
+        self.collections.insert(&nft_contract_id, &collection);
+    }
+
+    pub fn remove_collection(&mut self, nft_contract_id: AccountId) {
+        require!(self.has_role(Role::CollectionCurator, env::predecessor_account_id()), "Only collection curators can remove collections");
+        require!(self.collections.remove(&nft_contract_id).is_some(), "Collection not found");
+    }
+
+    pub fn get_collection(&self, nft_contract_id: AccountId) -> Option<Collection> {
+        self.collections.get(&nft_contract_id)
+    }
+
+    pub fn get_collections(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<CollectionWithContractId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.collections.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(nft_contract_id, collection)| CollectionWithContractId {
+                nft_contract_id,
+                collection,
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    //Verifica que el contrato NFT pueda listar según el modo del registro
+    //Check that the NFT contract can list according to the registry mode
+    pub fn internal_assert_collection_can_list(&self, nft_contract_id: &AccountId) {
+        let status = self.collections.get(nft_contract_id).map(|collection| collection.status);
+        match self.collection_mode {
+            CollectionMode::Allowlist => require!(
+                status == Some(CollectionStatus::Allowed),
+                "This NFT contract is not in the collection allowlist"
+            ),
+            CollectionMode::Denylist => require!(
+                status != Some(CollectionStatus::Denied),
+                "This NFT contract is denied in the collection registry"
+            ),
+            CollectionMode::Open => {}
+        }
+    }
+
+    //Colección aprobada explícitamente, se confía en el owner_id que reporta
+    //Explicitly allowed collection, the owner_id it reports is trusted
+    pub fn internal_assert_collection_trusted(&self, nft_contract_id: &AccountId) {
+        require!(
+            self.collections.get(nft_contract_id).map(|collection| collection.status) == Some(CollectionStatus::Allowed),
+            "Only allowed collections can list NFTs owned by another account than the signer"
+        );
+    }
+}
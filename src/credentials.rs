@@ -0,0 +1,194 @@
+use near_sdk::{require, PromiseOrValue};
+
+use crate::*;
+
+//Credencial soulbound que el marketplace emite al completar una eduform
+//Soulbound credential the marketplace issues when an eduform is completed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Credential {
+    pub owner_id: AccountId,
+    pub form_id: FormId,
+    pub issued_at: u64,
+}
+
+//Metadata del contrato NFT de credenciales (NEP-177)
+//Metadata of the credentials NFT contract (NEP-177)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CredentialContractMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+//Metadata de cada credencial (subconjunto de TokenMetadata de NEP-177)
+//Metadata of each credential (subset of NEP-177 TokenMetadata)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CredentialMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub media: Option<String>,
+    pub issued_at: Option<U64>,
+    //El FormId de la eduform completada, en JSON
+    //The FormId of the completed eduform, as JSON
+    pub extra: Option<String>,
+}
+
+//Token con el formato JSON de NEP-171
+//Token in the NEP-171 JSON format
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CredentialToken {
+    pub token_id: TokenId,
+    pub owner_id: AccountId,
+    pub metadata: CredentialMetadata,
+    pub approved_account_ids: HashMap<AccountId, u64>,
+}
+
+//Una credencial por eduform y cuenta: `form ID + DELIMETER + account ID`
+//One credential per eduform and account: `form ID + DELIMETER + account ID`
+pub(crate) fn credential_token_id(form_id: FormId, account_id: &AccountId) -> TokenId {
+    format!("{}{}{}", form_id, DELIMETER, account_id)
+}
+
+#[near_bindgen]
+impl Contract {
+    //Views NEP-171 / NEP-181 de las credenciales
+    //NEP-171 / NEP-181 views of the credentials
+
+    pub fn nft_metadata(&self) -> CredentialContractMetadata {
+        CredentialContractMetadata {
+            spec: "nft-1.0.0".to_string(),
+            name: "Evie Learning Credentials".to_string(),
+            symbol: "EVIEEDU".to_string(),
+        }
+    }
+
+    pub fn nft_token(&self, token_id: TokenId) -> Option<CredentialToken> {
+        self.credentials
+            .get(&token_id)
+            .map(|credential| self.internal_credential_token(token_id, credential))
+    }
+
+    pub fn nft_total_supply(&self) -> U128 {
+        U128(self.credentials.len() as u128)
+    }
+
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<CredentialToken> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.credentials.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(token_id, credential)| self.internal_credential_token(token_id, credential))
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.credentials_per_owner.get(&account_id).map(|token_ids| token_ids.len()).unwrap_or(0) as u128)
+    }
+
+    //Con marketplace_contract_id se comporta como antes y consulta ese marketplace (alias de nft_tokens_for_owner_marketplace)
+    //With marketplace_contract_id it behaves as before and queries that marketplace (alias of nft_tokens_for_owner_marketplace)
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+        marketplace_contract_id: Option<AccountId>,
+    ) -> PromiseOrValue<Vec<CredentialToken>> {
+        if let Some(marketplace_contract_id) = marketplace_contract_id {
+            return self.nft_tokens_for_owner_marketplace(account_id, from_index, limit, marketplace_contract_id).into();
+        }
+        let token_ids = if let Some(token_ids) = self.credentials_per_owner.get(&account_id) {
+            token_ids
+        } else {
+            return PromiseOrValue::Value(vec![]);
+        };
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        PromiseOrValue::Value(token_ids.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|token_id| self.nft_token(token_id).unwrap())
+            .collect())
+    }
+
+    //Las credenciales son soulbound, toda transferencia se rechaza
+    //Credentials are soulbound, every transfer is rejected
+    #[payable]
+    #[allow(unused_variables)]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        env::panic_str("Credentials are soulbound and can't be transferred");
+    }
+
+    #[payable]
+    #[allow(unused_variables)]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) {
+        env::panic_str("Credentials are soulbound and can't be transferred");
+    }
+}
+
+impl Contract {
+    //Emite la credencial de la eduform a la cuenta, desde la cuenta del marketplace
+    //Issue the credential of the eduform to the account, from the marketplace account
+    pub fn internal_mint_credential(&mut self, form_id: FormId, account_id: &AccountId) {
+        let token_id = credential_token_id(form_id, account_id);
+        require!(self.credentials.get(&token_id).is_none(), "Credential already issued");
+        self.credentials.insert(&token_id, &Credential {
+            owner_id: account_id.clone(),
+            form_id,
+            issued_at: env::block_timestamp(),
+        });
+
+        let mut token_ids = self.credentials_per_owner.get(account_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::CredentialsPerOwnerInner {
+                    account_id_hash: hash_account_id(account_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        token_ids.insert(&token_id);
+        self.credentials_per_owner.insert(account_id, &token_ids);
+
+        log_event("nep171", "1.0.0", "nft_mint", near_sdk::serde_json::json!({
+            "owner_id": account_id,
+            "token_ids": [token_id],
+        }));
+    }
+
+    pub fn internal_credential_token(&self, token_id: TokenId, credential: Credential) -> CredentialToken {
+        //La metadata se toma de la eduform, si fue borrada queda solo el FormId
+        //The metadata is taken from the eduform, if it was removed only the FormId remains
+        let form = self.edu_forms.get(&credential.form_id);
+        CredentialToken {
+            token_id,
+            owner_id: credential.owner_id,
+            metadata: CredentialMetadata {
+                title: form.as_ref().map(|form| format!("{} credential", form.title)),
+                description: form.as_ref().map(|form| format!("Completed the {} learning module on Evie", form.title)),
+                media: form.and_then(|form| form.images.into_iter().next()),
+                issued_at: Some(U64(credential.issued_at)),
+                extra: Some(near_sdk::serde_json::json!({ "form_id": credential.form_id }).to_string()),
+            },
+            approved_account_ids: HashMap::new(),
+        }
+    }
+}
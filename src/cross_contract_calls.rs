@@ -10,6 +10,7 @@ trait ExtParas {
         limit: Option<u64>,
     ) -> Vec<Token>;
     fn nft_approve(&mut self, token_id: TokenId, account_id: AccountId, msg: Option<String>);
+    fn nft_token(&self, token_id: TokenId) -> Option<Token>;
 }
 
 #[ext_contract(ext_nft_dos)]
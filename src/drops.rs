@@ -0,0 +1,200 @@
+use near_sdk::require;
+use near_sdk::serde_json::{json, Value};
+
+use crate::*;
+
+//Configuración de la venta primaria (drop) de una eduform
+//Configuration of the primary sale (drop) of an eduform
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DropConfig {
+    //Contrato del proyecto y metodo que mintea (ej. "nft_mint")
+    //Project contract and method that mints (e.g. "nft_mint")
+    pub nft_contract_id: AccountId,
+    pub mint_method: String,
+    //Objeto JSON con los argumentos del mint, el marketplace añade receiver_id
+    //JSON object with the mint arguments, the marketplace adds receiver_id
+    pub mint_args: Option<String>,
+    //Deposito adjunto al mint para el storage del contrato del proyecto, sale del precio
+    //Deposit attached to the mint for the storage of the project contract, it comes out of the price
+    pub mint_deposit: U128,
+    pub price: SalePriceInYoctoNear,
+    //Ventana de la venta en nanosegundos
+    //Sale window in nanoseconds
+    pub start_time: U64,
+    pub end_time: Option<U64>,
+    pub per_wallet_cap: Option<u32>,
+    //Si hay allowlist solo sus cuentas pueden comprar
+    //If there is an allowlist only its accounts can buy
+    pub allowlist_id: Option<AllowlistId>,
+    //Cuenta que recibe lo recaudado
+    //Account that receives the proceeds
+    pub treasury_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FormDrop {
+    pub config: DropConfig,
+    //Mints vendidos, incluidos los que están en curso (el limite es EduForm.supply)
+    //Mints sold, including the ones in flight (the limit is EduForm.supply)
+    pub minted: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    //Crea o actualiza el drop de una eduform, los mints ya vendidos se mantienen
+    //Create or update the drop of an eduform, the mints already sold are kept
+    pub fn set_form_drop(&mut self, form_id: FormId, config: DropConfig) {
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can set drops");
+        require!(self.edu_forms.get(&form_id).is_some(), "Form not found");
+        require!(!config.mint_method.is_empty(), "mint_method can't be empty");
+        require!(config.price.0 >= config.mint_deposit.0, "The price must cover the mint deposit");
+        if let Some(end_time) = config.end_time {
+            require!(end_time.0 > config.start_time.0, "end_time must be after start_time");
+        }
+        if let Some(allowlist_id) = config.allowlist_id {
+            require!(self.allowlists.get(&allowlist_id).is_some(), "Allowlist not found");
+        }
+        if let Some(mint_args) = &config.mint_args {
+            let args: Value = near_sdk::serde_json::from_str(mint_args).expect("mint_args is not valid JSON");
+            require!(args.is_object(), "mint_args must be a JSON object");
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        let minted = self.drops.get(&form_id).map(|form_drop| form_drop.minted).unwrap_or(U64(0));
+        self.drops.insert(&form_id, &FormDrop { config, minted });
+        self.internal_charge_pool_storage(initial_storage_usage);
+    }
+
+    pub fn remove_form_drop(&mut self, form_id: FormId) {
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can remove drops");
+        let initial_storage_usage = env::storage_usage();
+        require!(self.drops.remove(&form_id).is_some(), "Drop not found");
+        self.internal_charge_pool_storage(initial_storage_usage);
+    }
+
+    //Compra un mint del drop, el deposito debe ser >= al precio más el storage del mint y el exceso se devuelve
+    //Buy a mint from the drop, the deposit must be >= the price plus the storage of the mint and the excess is refunded
+    //proof solo se necesita si el drop usa una allowlist Merkle
+    //proof is only needed if the drop uses a Merkle allowlist
+    #[payable]
+    pub fn buy_from_drop(&mut self, form_id: FormId, proof: Option<Vec<Base64VecU8>>) -> Promise {
+        self.internal_assert_not_paused(PauseScope::Purchases);
+        require!(self.internal_is_form_public(form_id), "Form is not available");
+        let form = self.edu_forms.get(&form_id).unwrap();
+        let mut form_drop = self.drops.get(&form_id).expect("No drop for this form");
+        let config = form_drop.config.clone();
+        let buyer_id = env::predecessor_account_id();
+
+        //Verificamos la ventana de la venta
+        //Check the sale window
+        let now = env::block_timestamp();
+        require!(now >= config.start_time.0, "The drop has not started");
+        if let Some(end_time) = config.end_time {
+            require!(now < end_time.0, "The drop has ended");
+        }
+        if let Some(allowlist_id) = config.allowlist_id {
+            self.internal_assert_allowlisted(allowlist_id, &buyer_id, proof);
+        }
+        require!(form_drop.minted.0 < form.supply.0, "The drop is sold out");
+
+        let mint_key = format!("{}{}{}", form_id, DELIMETER, buyer_id);
+        let minted_by_buyer = self.drop_mints.get(&mint_key).unwrap_or(0);
+        if let Some(per_wallet_cap) = config.per_wallet_cap {
+            require!(minted_by_buyer < per_wallet_cap, format!("Only {} mints per wallet", per_wallet_cap));
+        }
+
+        //Reservamos el mint antes de la llamada, se revierte en resolve_drop_mint si falla
+        //Reserve the mint before the call, it is reverted in resolve_drop_mint if it fails
+        let initial_storage_usage = env::storage_usage();
+        form_drop.minted = U64(form_drop.minted.0 + 1);
+        self.drops.insert(&form_id, &form_drop);
+        self.drop_mints.insert(&mint_key, &(minted_by_buyer + 1));
+        let storage_cost = Balance::from(env::storage_usage() - initial_storage_usage) * STORAGE_PRICE_PER_BYTE;
+
+        //Cobramos el precio exacto más el storage del mint y devolvemos el exceso
+        //Charge the exact price plus the storage of the mint and refund the excess
+        let price = config.price.0;
+        let deposit = env::attached_deposit();
+        require!(
+            deposit >= price + storage_cost,
+            format!("Deposit must be at least the drop price plus storage: {}", price + storage_cost)
+        );
+        if deposit > price + storage_cost {
+            Promise::new(buyer_id.clone()).transfer(deposit - price - storage_cost);
+        }
+
+        let mut args: Value = config.mint_args
+            .as_deref()
+            .map(|mint_args| near_sdk::serde_json::from_str(mint_args).unwrap())
+            .unwrap_or_else(|| json!({}));
+        args["receiver_id"] = json!(buyer_id);
+
+        Promise::new(config.nft_contract_id)
+            .function_call(
+                config.mint_method,
+                args.to_string().into_bytes(),
+                config.mint_deposit.0,
+                GAS_FOR_DROP_MINT,
+            )
+            .then(ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_DROP_MINT)
+                .resolve_drop_mint(
+                    form_id,
+                    buyer_id,
+                    U128(price),
+                    config.treasury_id,
+                    U128(price - config.mint_deposit.0),
+                ))
+    }
+
+    //Si el mint salió bien pagamos al treasury, si no devolvemos el precio al comprador y liberamos el mint
+    //If the mint succeeded we pay the treasury, otherwise we refund the price to the buyer and release the mint
+    #[private]
+    pub fn resolve_drop_mint(
+        &mut self,
+        form_id: FormId,
+        buyer_id: AccountId,
+        price: U128,
+        treasury_id: AccountId,
+        proceeds: U128,
+    ) -> bool {
+        if promise_result_as_success().is_some() {
+            if proceeds.0 > 0 {
+                Promise::new(treasury_id).transfer(proceeds.0);
+            }
+            return true;
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        if let Some(mut form_drop) = self.drops.get(&form_id) {
+            form_drop.minted = U64(form_drop.minted.0.saturating_sub(1));
+            self.drops.insert(&form_id, &form_drop);
+        }
+        let mint_key = format!("{}{}{}", form_id, DELIMETER, buyer_id);
+        match self.drop_mints.get(&mint_key).unwrap_or(0) {
+            0 | 1 => {
+                self.drop_mints.remove(&mint_key);
+            }
+            minted_by_buyer => {
+                self.drop_mints.insert(&mint_key, &(minted_by_buyer - 1));
+            }
+        }
+        //El deposito del mint fallido vuelve a este contrato, devolvemos el precio completo y el storage liberado
+        //The deposit of the failed mint comes back to this contract, we refund the whole price and the freed storage
+        let refund = price.0 + Balance::from(initial_storage_usage.saturating_sub(env::storage_usage())) * STORAGE_PRICE_PER_BYTE;
+        env::log_str(&format!("Drop mint failed for {}, refunding {}", buyer_id, refund));
+        Promise::new(buyer_id).transfer(refund);
+        false
+    }
+
+    pub fn get_form_drop(&self, form_id: FormId) -> Option<FormDrop> {
+        self.drops.get(&form_id)
+    }
+
+    pub fn get_drop_mints_by(&self, form_id: FormId, account_id: AccountId) -> u32 {
+        self.drop_mints.get(&format!("{}{}{}", form_id, DELIMETER, account_id)).unwrap_or(0)
+    }
+}
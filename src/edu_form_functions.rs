@@ -6,59 +6,199 @@ trait EduFormFunctions {
     fn add_form(&mut self, form: EduForm);
     fn remove_form(&mut self, form_id: u32);
     fn update_form(&mut self, form_id: u32, form: EduForm);
-    fn get_forms(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<EduFormWithId>;
-    fn get_form_by_id(&self, form_id: u32) -> EduFormWithId;
-    fn remove_all_forms(&mut self);
+    fn get_forms(&self, from_index: Option<U128>, limit: Option<u64>, locale: Option<String>) -> Vec<EduFormWithId>;
+    fn get_form_by_id(&self, form_id: u32, locale: Option<String>) -> EduFormWithId;
+    fn get_form_by_nft_contract_id(&self, nft_contract_id: AccountId) -> Option<EduFormWithId>;
     fn get_forms_count(&self) -> u64;
 }
 
 #[near_bindgen]
 impl EduFormFunctions for Contract {
     fn add_form(&mut self, form: EduForm) {
-        require!((self.admins.contains(&env::signer_account_id() ) || self.owner == env::signer_account_id()), "Only admins or the owner can add forms");
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can add forms");
+        assert_valid_form(&form);
+        let initial_storage_usage = env::storage_usage();
         let form_id: u32 = self.edu_form_number;
+        self.internal_link_form_nft_contracts(form_id, &form.nft_contract_ids);
+        self.internal_index_form(form_id, &form);
         self.edu_forms.insert(&form_id, &form);
+        //Las eduforms añadidas por moderadores se aprueban directamente
+        //The eduforms added by moderators are approved directly
+        self.form_states.insert(&form_id, &FormState {
+            submitter: env::predecessor_account_id(),
+            status: FormStatus::Approved,
+            storage_used: 0,
+            paid_by_pool: true,
+            edited_by: env::predecessor_account_id(),
+            edited_at: env::block_timestamp(),
+            removed_at: None,
+        });
         self.edu_form_number += 1;
+        //El storage de las eduforms de moderadores lo paga el pool
+        //The storage of the moderators' eduforms is paid by the pool
+        self.internal_charge_form_storage(form_id, initial_storage_usage);
     }
+    //Borrado suave, la eduform se oculta pero se puede restaurar con restore_form
+    //Soft delete, the eduform is hidden but can be restored with restore_form
     fn remove_form(&mut self, form_id: u32) {
-        require!((self.admins.contains(&env::signer_account_id() ) || self.owner == env::signer_account_id()), "Only admins or the owner can remove forms");
-        self.edu_forms.remove(&form_id);
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can remove forms");
+        let form = self.edu_forms.get(&form_id).expect("Form not found");
+        let mut state = self.internal_form_state(form_id);
+        require!(state.removed_at.is_none(), "Form is already removed");
+        let initial_storage_usage = env::storage_usage();
+        self.internal_unlink_form_nft_contracts(form_id, &form.nft_contract_ids);
+        state.removed_at = Some(env::block_timestamp());
+        self.form_states.insert(&form_id, &state);
+        self.pending_forms.remove(&form_id);
+        self.internal_charge_form_storage(form_id, initial_storage_usage);
     }
     fn update_form(&mut self, form_id: u32, form: EduForm) {
-        require!((self.admins.contains(&env::signer_account_id() ) || self.owner == env::signer_account_id()), "Only admins or the owner can update forms");
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can update forms");
+        //No se pueden crear eduforms con IDs que no se hayan emitido
+        //Eduforms can't be created with IDs that were never issued
+        let old_form = self.edu_forms.get(&form_id).expect("Form not found");
+        //Una eduform borrada se restaura primero con restore_form
+        //A removed eduform is restored first with restore_form
+        let mut state = self.internal_form_state(form_id);
+        require!(state.removed_at.is_none(), "Form was removed, restore it first");
+        assert_valid_form(&form);
+        let initial_storage_usage = env::storage_usage();
+        self.internal_unlink_form_nft_contracts(form_id, &old_form.nft_contract_ids);
+        self.internal_unindex_form(form_id, &old_form);
+        self.internal_index_form(form_id, &form);
+        //Guardamos la versión anterior en el historial
+        //Keep the previous version in the history
+        self.internal_push_form_version(form_id, old_form, &state);
+        state.edited_by = env::predecessor_account_id();
+        state.edited_at = env::block_timestamp();
+        self.form_states.insert(&form_id, &state);
+        //Solo las eduforms aprobadas quedan enlazadas a sus contratos NFT
+        //Only approved eduforms are linked to their NFT contracts
+        if self.internal_is_form_public(form_id) {
+            self.internal_link_form_nft_contracts(form_id, &form.nft_contract_ids);
+        }
         self.edu_forms.insert(&form_id, &form);
+        self.internal_charge_form_storage(form_id, initial_storage_usage);
     }
-    fn get_forms(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<EduFormWithId> {
+    //Si se pasa locale, title y descriptions vienen resueltos en ese idioma
+    //If locale is passed, title and descriptions come resolved in that language
+    fn get_forms(&self, from_index: Option<U128>, limit: Option<u64>, locale: Option<String>) -> Vec<EduFormWithId> {
         // let forms: &Vector<u32> = self.edu_forms.keys_as_vector();
         let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
         let keys: Vec<u32> = self.edu_forms.keys_as_vector().to_vec();
         
+        //Solo se listan las eduforms aprobadas y no borradas
+        //Only approved and not removed eduforms are listed
         let forms: Vec<EduFormWithId> = keys.iter()
+            .filter(|key| self.internal_is_form_public(**key))
             .skip(start as usize)
             .take(limit.unwrap_or(50) as usize)
             .map(|key: &u32| {
                 let form = self.edu_forms.get(key).unwrap();
-                EduFormWithId {
-                    id: key.clone(),
-                    form: form.clone(),
-                }
+                self.internal_form_with_id(*key, form.resolve_locale(locale.as_deref()))
             })
             .collect();
         forms
     }
-    fn get_form_by_id(&self, form_id: u32) -> EduFormWithId {
+    fn get_form_by_id(&self, form_id: u32, locale: Option<String>) -> EduFormWithId {
         let pre_edu_form = self.edu_forms.get(&form_id).unwrap().clone();
-        let form = EduFormWithId {
-            id: form_id,
-            form: pre_edu_form,
-        };
-        form
+        self.internal_form_with_id(form_id, pre_edu_form.resolve_locale(locale.as_deref()))
     }
-    fn remove_all_forms(&mut self) {
-        require!(self.owner == env::signer_account_id(), "Only owner can clear forms");
-        self.edu_forms.clear();
+    //Busca la eduform enlazada al contrato NFT, o la que tenga asignada en el registro de colecciones
+    //Look up the eduform linked to the NFT contract, or the one assigned in the collection registry
+    fn get_form_by_nft_contract_id(&self, nft_contract_id: AccountId) -> Option<EduFormWithId> {
+        self.internal_form_by_nft_contract_id(&nft_contract_id)
     }
     fn get_forms_count(&self) -> u64 {
         self.edu_forms.len() as u64
     }
 }
+
+impl EduForm {
+    //Resuelve title y descriptions al idioma pedido: primero el idioma exacto ("es-MX"),
+    //despues el idioma base ("es") y si no hay traducción se queda el idioma por defecto
+    //Resolve title and descriptions to the requested language: first the exact language ("es-MX"),
+    //then the base language ("es") and if there is no translation the default language stays
+    pub fn resolve_locale(mut self, locale: Option<&str>) -> EduForm {
+        let locale = if let Some(locale) = locale {
+            locale
+        } else {
+            return self;
+        };
+        let base_locale = locale.split('-').next().unwrap_or(locale);
+        let resolved_locale = [locale, base_locale].into_iter()
+            .find(|candidate| self.translations.contains_key(*candidate))
+            .map(str::to_string);
+
+        if let Some(resolved_locale) = resolved_locale {
+            let translation = self.translations.remove(&resolved_locale).unwrap();
+            self.title = translation.title;
+            self.descriptions = translation.descriptions;
+            self.locale = resolved_locale;
+        }
+        //La eduform resuelta solo lleva los textos del idioma elegido
+        //The resolved eduform only carries the texts of the chosen language
+        self.translations.clear();
+        self
+    }
+}
+
+impl Contract {
+    pub fn internal_form_by_nft_contract_id(&self, nft_contract_id: &AccountId) -> Option<EduFormWithId> {
+        let form_id = self.form_by_nft_contract_id.get(nft_contract_id).or_else(|| {
+            self.collections.get(nft_contract_id).and_then(|collection| collection.edu_form_id)
+        })?;
+        if !self.internal_is_form_public(form_id) {
+            return None;
+        }
+        self.edu_forms.get(&form_id).map(|form| self.internal_form_with_id(form_id, form))
+    }
+
+    //Las eduforms sin estado son anteriores a la moderación y se consideran aprobadas
+    //Eduforms without state predate moderation and are considered approved
+    pub fn internal_form_with_id(&self, form_id: FormId, form: EduForm) -> EduFormWithId {
+        let state = self.form_states.get(&form_id);
+        EduFormWithId {
+            id: form_id,
+            form,
+            status: state.as_ref().map(|state| state.status.clone()).unwrap_or(FormStatus::Approved),
+            submitter: state.as_ref().map(|state| state.submitter.clone()),
+            removed_at: state.and_then(|state| state.removed_at).map(U64),
+        }
+    }
+
+    //Una eduform es publica si está aprobada y no fue borrada
+    //An eduform is public if it is approved and was not removed
+    pub fn internal_is_form_public(&self, form_id: FormId) -> bool {
+        self.form_states
+            .get(&form_id)
+            .map(|state| state.status == FormStatus::Approved && state.removed_at.is_none())
+            .unwrap_or(true)
+    }
+
+    //Enlaza los contratos NFT a la eduform, un contrato solo puede pertenecer a una eduform
+    //Link the NFT contracts to the eduform, a contract can only belong to one eduform
+    pub fn internal_link_form_nft_contracts(&mut self, form_id: FormId, nft_contract_ids: &[AccountId]) {
+        for nft_contract_id in nft_contract_ids {
+            if let Some(linked_form_id) = self.form_by_nft_contract_id.get(nft_contract_id) {
+                require!(
+                    linked_form_id == form_id,
+                    format!("{} is already linked to form {}", nft_contract_id, linked_form_id)
+                );
+            }
+            self.form_by_nft_contract_id.insert(nft_contract_id, &form_id);
+        }
+    }
+
+    //Solo quita los enlaces que apuntan a esta eduform
+    //Only remove the links that point to this eduform
+    pub fn internal_unlink_form_nft_contracts(&mut self, form_id: FormId, nft_contract_ids: &[AccountId]) {
+        for nft_contract_id in nft_contract_ids {
+            if self.form_by_nft_contract_id.get(nft_contract_id) == Some(form_id) {
+                self.form_by_nft_contract_id.remove(nft_contract_id);
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use near_sdk::require;
+
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    //Versiones anteriores de una eduform, de la más antigua a la más reciente
+    //Previous versions of an eduform, from the oldest to the most recent
+    pub fn get_form_history(&self, form_id: FormId, from_index: Option<U128>, limit: Option<u64>) -> Vec<FormVersion> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        match self.form_history.get(&form_id) {
+            Some(history) => history.iter()
+                .skip(start as usize)
+                .take(limit.unwrap_or(50) as usize)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn get_form_history_count(&self, form_id: FormId) -> u64 {
+        self.form_history.get(&form_id).map(|history| history.len()).unwrap_or(0)
+    }
+
+    //Abre una propuesta RemoveAllForms, devuelve su id
+    //Open a RemoveAllForms proposal, return its id
+    #[payable]
+    pub fn request_remove_all_forms(&mut self) -> U64 {
+        U64(self.propose(ProposalAction::RemoveAllForms))
+    }
+
+    //Continúa una propuesta RemoveAllForms ya ejecutada hasta que no queden eduforms
+    //Continue an already executed RemoveAllForms proposal until no eduforms are left
+    pub fn remove_all_forms_batch(&mut self, limit: Option<u64>) {
+        require!(self.has_role(Role::Admin, env::predecessor_account_id()), "Only admins can remove all forms");
+        require!(self.remove_all_forms_in_progress, "There is no RemoveAllForms in progress");
+        self.internal_remove_all_forms(limit.unwrap_or(REMOVE_ALL_FORMS_BATCH));
+    }
+
+    pub fn is_removing_all_forms(&self) -> bool {
+        self.remove_all_forms_in_progress
+    }
+
+    //Restaura una eduform borrada con remove_form
+    //Restore an eduform removed with remove_form
+    pub fn restore_form(&mut self, form_id: FormId) {
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can restore forms");
+        let form = self.edu_forms.get(&form_id).expect("Form not found");
+        let mut state = self.internal_form_state(form_id);
+        require!(state.removed_at.is_some(), "Form is not removed");
+        let initial_storage_usage = env::storage_usage();
+        state.removed_at = None;
+        self.form_states.insert(&form_id, &state);
+        match state.status {
+            FormStatus::Approved => self.internal_link_form_nft_contracts(form_id, &form.nft_contract_ids),
+            FormStatus::Pending => {
+                self.pending_forms.insert(&form_id);
+            }
+            _ => {}
+        }
+        self.internal_charge_form_storage(form_id, initial_storage_usage);
+    }
+
+    //Borra definitivamente una eduform ya borrada, con su historial, y libera su storage
+    //Permanently delete an already removed eduform, with its history, and release its storage
+    pub fn purge_form(&mut self, form_id: FormId) {
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can purge forms");
+        require!(self.internal_form_state(form_id).removed_at.is_some(), "Only removed forms can be purged");
+        self.internal_purge_form(form_id);
+    }
+}
+
+impl Contract {
+    //Borra hasta limit eduforms, la propuesta RemoveAllForms borra el primer lote y remove_all_forms_batch el resto
+    //Delete up to limit eduforms, the RemoveAllForms proposal deletes the first batch and remove_all_forms_batch the rest
+    pub fn internal_remove_all_forms(&mut self, limit: u64) {
+        self.remove_all_forms_in_progress = true;
+        let form_ids: Vec<FormId> = self.edu_forms.keys().take(limit as usize).collect();
+        for form_id in form_ids {
+            self.internal_purge_form(form_id);
+        }
+        if self.edu_forms.is_empty() {
+            self.pending_forms.clear();
+            self.remove_all_forms_in_progress = false;
+        }
+        log_event("evie", "1.0.0", "remove_all_forms_batch", near_sdk::serde_json::json!({
+            "remaining": U64(self.edu_forms.len()),
+        }));
+    }
+
+    pub fn internal_push_form_version(&mut self, form_id: FormId, form: EduForm, state: &FormState) {
+        let mut history = self.form_history.get(&form_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::FormHistoryInner { form_id }.try_to_vec().unwrap())
+        });
+        history.push(&FormVersion {
+            form,
+            editor: state.edited_by.clone(),
+            timestamp: U64(state.edited_at),
+        });
+        self.form_history.insert(&form_id, &history);
+    }
+
+    //Borra la eduform, su estado y su historial, liberando el storage a quien lo pagó
+    //Delete the eduform, its state and its history, releasing the storage to whoever paid it
+    pub fn internal_purge_form(&mut self, form_id: FormId) {
+        if let Some(form) = self.edu_forms.remove(&form_id) {
+            self.internal_unlink_form_nft_contracts(form_id, &form.nft_contract_ids);
+            self.internal_unindex_form(form_id, &form);
+        }
+        if let Some(mut history) = self.form_history.remove(&form_id) {
+            history.clear();
+        }
+        if let Some(state) = self.form_states.remove(&form_id) {
+            self.internal_release_form_storage(&state, state.storage_used);
+        }
+        self.pending_forms.remove(&form_id);
+        //El storage del quiz y del drop vuelve al pool
+        //The storage of the quiz and the drop goes back to the pool
+        let initial_storage_usage = env::storage_usage();
+        let removed_quiz = self.form_quizzes.remove(&form_id).is_some();
+        let removed_drop = self.drops.remove(&form_id).is_some();
+        if removed_quiz || removed_drop {
+            self.internal_charge_pool_storage(initial_storage_usage);
+        }
+    }
+}
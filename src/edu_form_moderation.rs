@@ -0,0 +1,133 @@
+use near_sdk::require;
+
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    //Cualquier cuenta puede enviar una eduform, queda pendiente hasta que un moderador la modere
+    //Any account can submit an eduform, it stays pending until a moderator moderates it
+    #[payable]
+    pub fn submit_form(&mut self, form: EduForm) -> FormId {
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        assert_valid_form(&form);
+        let submitter = env::predecessor_account_id();
+        self.internal_add_storage_deposit(&submitter);
+
+        let initial_storage_usage = env::storage_usage();
+        let form_id: FormId = self.edu_form_number;
+        self.internal_index_form(form_id, &form);
+        self.edu_forms.insert(&form_id, &form);
+        self.form_states.insert(&form_id, &FormState {
+            submitter: submitter.clone(),
+            status: FormStatus::Pending,
+            storage_used: 0,
+            paid_by_pool: false,
+            edited_by: submitter,
+            edited_at: env::block_timestamp(),
+            removed_at: None,
+        });
+        self.pending_forms.insert(&form_id);
+        self.edu_form_number += 1;
+        //El autor paga el storage de su eduform con su deposito
+        //The author pays the storage of their eduform with their deposit
+        self.internal_charge_form_storage(form_id, initial_storage_usage);
+        form_id
+    }
+
+    //El autor puede editar su eduform mientras esté pendiente o con cambios solicitados
+    //The author can edit their eduform while it is pending or has changes requested
+    #[payable]
+    pub fn edit_submitted_form(&mut self, form_id: FormId, form: EduForm) {
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        assert_valid_form(&form);
+        let mut state = self.form_states.get(&form_id).expect("Form not found");
+        require!(state.submitter == env::predecessor_account_id(), "Only the submitter can edit this form");
+        require!(state.removed_at.is_none(), "Form was removed");
+        require!(
+            matches!(state.status, FormStatus::Pending | FormStatus::ChangesRequested { .. }),
+            "Only pending forms can be edited"
+        );
+        self.internal_add_storage_deposit(&state.submitter);
+
+        let initial_storage_usage = env::storage_usage();
+        let old_form = self.edu_forms.get(&form_id).expect("Form not found");
+        self.internal_unindex_form(form_id, &old_form);
+        self.internal_index_form(form_id, &form);
+        self.internal_push_form_version(form_id, old_form, &state);
+        state.edited_by = state.submitter.clone();
+        state.edited_at = env::block_timestamp();
+        self.edu_forms.insert(&form_id, &form);
+        //Al editarla vuelve a la cola de moderación
+        //When edited it goes back to the moderation queue
+        state.status = FormStatus::Pending;
+        self.form_states.insert(&form_id, &state);
+        self.pending_forms.insert(&form_id);
+        self.internal_charge_form_storage(form_id, initial_storage_usage);
+    }
+
+    //El autor puede retirar su eduform si no está aprobada, liberando su storage
+    //The author can withdraw their eduform if it is not approved, releasing its storage
+    pub fn withdraw_submitted_form(&mut self, form_id: FormId) {
+        let state = self.form_states.get(&form_id).expect("Form not found");
+        require!(state.submitter == env::predecessor_account_id(), "Only the submitter can withdraw this form");
+        require!(state.status != FormStatus::Approved, "Approved forms can only be removed by form moderators");
+        self.internal_purge_form(form_id);
+    }
+
+    pub fn approve_form(&mut self, form_id: FormId) {
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can approve forms");
+        let form = self.edu_forms.get(&form_id).expect("Form not found");
+        self.internal_set_form_status(form_id, FormStatus::Approved);
+        self.internal_link_form_nft_contracts(form_id, &form.nft_contract_ids);
+    }
+
+    pub fn reject_form(&mut self, form_id: FormId, reason: String) {
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can reject forms");
+        let form = self.edu_forms.get(&form_id).expect("Form not found");
+        self.internal_unlink_form_nft_contracts(form_id, &form.nft_contract_ids);
+        self.internal_set_form_status(form_id, FormStatus::Rejected { reason });
+    }
+
+    pub fn request_form_changes(&mut self, form_id: FormId, reason: String) {
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can request changes");
+        let form = self.edu_forms.get(&form_id).expect("Form not found");
+        self.internal_unlink_form_nft_contracts(form_id, &form.nft_contract_ids);
+        self.internal_set_form_status(form_id, FormStatus::ChangesRequested { reason });
+    }
+
+    //Cola de moderación, eduforms pendientes en orden de envío
+    //Moderation queue, pending eduforms in submission order
+    pub fn get_moderation_queue(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<EduFormWithId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.pending_forms.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|form_id| self.internal_form_with_id(form_id, self.edu_forms.get(&form_id).unwrap()))
+            .collect()
+    }
+
+    pub fn get_moderation_queue_count(&self) -> u64 {
+        self.pending_forms.len()
+    }
+}
+
+impl Contract {
+    pub fn internal_set_form_status(&mut self, form_id: FormId, status: FormStatus) {
+        let mut state = self.internal_form_state(form_id);
+        require!(state.removed_at.is_none(), "Form was removed, restore it first");
+        state.status = status;
+        self.form_states.insert(&form_id, &state);
+        self.pending_forms.remove(&form_id);
+    }
+
+    //Suma el deposito adjunto al balance de storage de la cuenta
+    //Add the attached deposit to the storage balance of the account
+    pub fn internal_add_storage_deposit(&mut self, account_id: &AccountId) {
+        let deposit = env::attached_deposit();
+        if deposit > 0 {
+            let balance = self.storage_deposits.get(account_id).unwrap_or(0) + deposit;
+            self.storage_deposits.insert(account_id, &balance);
+        }
+    }
+}
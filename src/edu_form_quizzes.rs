@@ -0,0 +1,155 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Pregunta de un quiz, la respuesta correcta se guarda como hash
+//Question of a quiz, the correct answer is stored as a hash
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizQuestion {
+    pub prompt: String,
+    pub options: Vec<String>,
+    //sha256(`form ID + DELIMETER + indice de la pregunta + DELIMETER + respuesta`)
+    //sha256(`form ID + DELIMETER + question index + DELIMETER + answer`)
+    pub answer_hash: Base64VecU8,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Quiz {
+    pub questions: Vec<QuizQuestion>,
+    //Respuestas correctas necesarias para completar el quiz
+    //Correct answers needed to complete the quiz
+    pub pass_score: u32,
+}
+
+//Hash con el que se compromete la respuesta de una pregunta
+//Hash the answer of a question is committed with
+pub(crate) fn hash_quiz_answer(form_id: FormId, question_index: usize, answer: &str) -> Vec<u8> {
+    env::sha256(format!("{}{}{}{}{}", form_id, DELIMETER, question_index, DELIMETER, answer).as_bytes())
+}
+
+#[near_bindgen]
+impl Contract {
+    //Añade o reemplaza el quiz de una eduform, el storage lo paga el pool
+    //Add or replace the quiz of an eduform, the storage is paid by the pool
+    pub fn set_form_quiz(&mut self, form_id: FormId, quiz: Quiz) {
+        self.internal_assert_not_paused(PauseScope::FormEdits);
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can set quizzes");
+        require!(self.edu_forms.get(&form_id).is_some(), "Form not found");
+        require!(
+            !quiz.questions.is_empty() && quiz.questions.len() <= MAX_QUIZ_QUESTIONS,
+            format!("A quiz must have between 1 and {} questions", MAX_QUIZ_QUESTIONS)
+        );
+        require!(
+            quiz.pass_score > 0 && quiz.pass_score as usize <= quiz.questions.len(),
+            "pass_score must be between 1 and the number of questions"
+        );
+        for question in &quiz.questions {
+            require!(question.answer_hash.0.len() == 32, "answer_hash must be a sha256 hash");
+            require!(question.prompt.len() <= MAX_FORM_DESCRIPTION_LEN, format!("Prompts can't exceed {} bytes", MAX_FORM_DESCRIPTION_LEN));
+            require!(question.options.iter().all(|option| option.len() <= MAX_FORM_URL_LEN), format!("Options can't exceed {} bytes", MAX_FORM_URL_LEN));
+        }
+        let initial_storage_usage = env::storage_usage();
+        self.form_quizzes.insert(&form_id, &quiz);
+        self.internal_charge_pool_storage(initial_storage_usage);
+    }
+
+    pub fn remove_form_quiz(&mut self, form_id: FormId) {
+        require!(self.has_role(Role::FormModerator, env::predecessor_account_id()), "Only form moderators can remove quizzes");
+        let initial_storage_usage = env::storage_usage();
+        require!(self.form_quizzes.remove(&form_id).is_some(), "Quiz not found");
+        self.internal_charge_pool_storage(initial_storage_usage);
+    }
+
+    pub fn get_form_quiz(&self, form_id: FormId) -> Option<Quiz> {
+        self.form_quizzes.get(&form_id)
+    }
+
+    //El usuario envía sus respuestas, si alcanza pass_score se registra la eduform como completada
+    //The user submits their answers, if they reach pass_score the eduform is recorded as completed
+    //El storage de la completación y la credencial se bloquea del deposito del usuario, como en submit_form
+    //The storage of the completion and the credential is locked from the user's deposit, as in submit_form
+    #[payable]
+    pub fn submit_quiz_answers(&mut self, form_id: FormId, answers: Vec<String>) -> bool {
+        require!(self.internal_is_form_public(form_id), "Form is not available");
+        let quiz = self.form_quizzes.get(&form_id).expect("Quiz not found");
+        require!(answers.len() == quiz.questions.len(), "One answer per question is required");
+
+        let account_id = env::predecessor_account_id();
+        require!(!self.has_completed_form(account_id.clone(), form_id), "Form already completed");
+        self.internal_add_storage_deposit(&account_id);
+
+        let score = quiz.questions.iter()
+            .zip(answers.iter())
+            .enumerate()
+            .filter(|(index, (question, answer))| hash_quiz_answer(form_id, *index, answer) == question.answer_hash.0)
+            .count();
+        if score < quiz.pass_score as usize {
+            env::log_str(&format!("Quiz failed with {} of {} correct answers", score, quiz.pass_score));
+            return false;
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        self.internal_record_form_completion(form_id, &account_id);
+        //Emitimos la credencial soulbound de la eduform
+        //Issue the soulbound credential of the eduform
+        self.internal_mint_credential(form_id, &account_id);
+        let cost = Balance::from(env::storage_usage() - initial_storage_usage) * STORAGE_PRICE_PER_BYTE;
+        self.internal_lock_account_storage(&account_id, cost);
+        true
+    }
+
+    //Cuentas que completaron una eduform
+    //Accounts that completed an eduform
+    pub fn get_form_completions(&self, form_id: FormId, from_index: Option<U128>, limit: Option<u64>) -> Vec<AccountId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        match self.form_completions.get(&form_id) {
+            Some(accounts) => accounts.iter()
+                .skip(start as usize)
+                .take(limit.unwrap_or(50) as usize)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn get_form_completions_count(&self, form_id: FormId) -> u64 {
+        self.form_completions.get(&form_id).map(|accounts| accounts.len()).unwrap_or(0)
+    }
+
+    //Eduforms completadas por una cuenta
+    //Eduforms completed by an account
+    pub fn get_completed_forms(&self, account_id: AccountId) -> Vec<FormId> {
+        self.completed_forms.get(&account_id).map(|form_ids| form_ids.to_vec()).unwrap_or_default()
+    }
+
+    pub fn has_completed_form(&self, account_id: AccountId, form_id: FormId) -> bool {
+        self.completed_forms
+            .get(&account_id)
+            .map(|form_ids| form_ids.contains(&form_id))
+            .unwrap_or(false)
+    }
+}
+
+impl Contract {
+    pub fn internal_record_form_completion(&mut self, form_id: FormId, account_id: &AccountId) {
+        let mut accounts = self.form_completions.get(&form_id).unwrap_or_else(|| {
+            UnorderedSet::new(StorageKey::FormCompletionsInner { form_id }.try_to_vec().unwrap())
+        });
+        accounts.insert(account_id);
+        self.form_completions.insert(&form_id, &accounts);
+
+        let mut form_ids = self.completed_forms.get(account_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::CompletedFormsInner {
+                    account_id_hash: hash_account_id(account_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        form_ids.insert(&form_id);
+        self.completed_forms.insert(account_id, &form_ids);
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use std::ops::Bound;
+
+use crate::*;
+
+//Tags, categorías y titulos se indexan en minusculas y sin espacios al inicio o final
+//Tags, categories and titles are indexed lowercase and without leading or trailing spaces
+fn normalize_search_key(value: &str) -> String {
+    value.trim().to_lowercase()
+}
+
+fn form_titles(form: &EduForm) -> impl Iterator<Item = &String> {
+    std::iter::once(&form.title).chain(form.translations.values().map(|translation| &translation.title))
+}
+
+fn hash_search_key(key: &str) -> CryptoHash {
+    let mut hash = CryptoHash::default();
+    hash.copy_from_slice(&env::sha256(key.as_bytes()));
+    hash
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_forms_by_tag(&self, tag: String, from_index: Option<U128>, limit: Option<u64>) -> Vec<EduFormWithId> {
+        let form_ids = self.forms_by_tag.get(&normalize_search_key(&tag));
+        self.internal_public_forms_from_set(form_ids, from_index, limit)
+    }
+
+    pub fn get_forms_by_category(&self, category: String, from_index: Option<U128>, limit: Option<u64>) -> Vec<EduFormWithId> {
+        let form_ids = self.forms_by_category.get(&normalize_search_key(&category));
+        self.internal_public_forms_from_set(form_ids, from_index, limit)
+    }
+
+    //Busca eduforms cuyo titulo empiece con el prefijo, sin importar mayusculas
+    //Search eduforms whose title starts with the prefix, case-insensitive
+    pub fn get_forms_by_title_prefix(&self, prefix: String, from_index: Option<U128>, limit: Option<u64>) -> Vec<EduFormWithId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+        let prefix = normalize_search_key(&prefix);
+        //Una eduform puede coincidir por el titulo de varios idiomas
+        //An eduform can match by the title of several languages
+        let mut seen: HashSet<FormId> = HashSet::new();
+
+        self.forms_by_title
+            .range((Bound::Included(prefix.clone()), Bound::Unbounded))
+            .take_while(|(title_key, _)| title_key.starts_with(&prefix))
+            .filter(|(_, form_id)| seen.insert(*form_id) && self.internal_is_form_public(*form_id))
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(_, form_id)| self.internal_form_with_id(form_id, self.edu_forms.get(&form_id).unwrap()))
+            .collect()
+    }
+}
+
+impl Contract {
+    pub fn internal_public_forms_from_set(
+        &self,
+        form_ids: Option<UnorderedSet<FormId>>,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<EduFormWithId> {
+        let form_ids = if let Some(form_ids) = form_ids {
+            form_ids
+        } else {
+            return vec![];
+        };
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        form_ids.iter()
+            .filter(|form_id| self.internal_is_form_public(*form_id))
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|form_id| self.internal_form_with_id(form_id, self.edu_forms.get(&form_id).unwrap()))
+            .collect()
+    }
+
+    //Añade la eduform a los indices de tag, categoría y titulo
+    //Add the eduform to the tag, category and title indexes
+    pub fn internal_index_form(&mut self, form_id: FormId, form: &EduForm) {
+        for tag in &form.tags {
+            let tag = normalize_search_key(tag);
+            let mut form_ids = self.forms_by_tag.get(&tag).unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::FormsByTagInner { tag_hash: hash_search_key(&tag) }
+                        .try_to_vec()
+                        .unwrap(),
+                )
+            });
+            form_ids.insert(&form_id);
+            self.forms_by_tag.insert(&tag, &form_ids);
+        }
+
+        if let Some(category) = &form.category {
+            let category = normalize_search_key(category);
+            let mut form_ids = self.forms_by_category.get(&category).unwrap_or_else(|| {
+                UnorderedSet::new(
+                    StorageKey::FormsByCategoryInner { category_hash: hash_search_key(&category) }
+                        .try_to_vec()
+                        .unwrap(),
+                )
+            });
+            form_ids.insert(&form_id);
+            self.forms_by_category.insert(&category, &form_ids);
+        }
+
+        //Se indexa el titulo de cada idioma
+        //The title of each language is indexed
+        for title in form_titles(form) {
+            let title_key = format!("{}{}{}", normalize_search_key(title), DELIMETER, form_id);
+            self.forms_by_title.insert(&title_key, &form_id);
+        }
+    }
+
+    //Quita la eduform de los indices, los sets vacios se eliminan
+    //Remove the eduform from the indexes, empty sets are deleted
+    pub fn internal_unindex_form(&mut self, form_id: FormId, form: &EduForm) {
+        for tag in &form.tags {
+            let tag = normalize_search_key(tag);
+            if let Some(mut form_ids) = self.forms_by_tag.get(&tag) {
+                form_ids.remove(&form_id);
+                if form_ids.is_empty() {
+                    self.forms_by_tag.remove(&tag);
+                } else {
+                    self.forms_by_tag.insert(&tag, &form_ids);
+                }
+            }
+        }
+
+        if let Some(category) = &form.category {
+            let category = normalize_search_key(category);
+            if let Some(mut form_ids) = self.forms_by_category.get(&category) {
+                form_ids.remove(&form_id);
+                if form_ids.is_empty() {
+                    self.forms_by_category.remove(&category);
+                } else {
+                    self.forms_by_category.insert(&category, &form_ids);
+                }
+            }
+        }
+
+        for title in form_titles(form) {
+            let title_key = format!("{}{}{}", normalize_search_key(title), DELIMETER, form_id);
+            self.forms_by_title.remove(&title_key);
+        }
+    }
+}
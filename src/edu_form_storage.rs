@@ -0,0 +1,109 @@
+use near_sdk::require;
+
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    //Los fee managers financian el pool que paga el storage de las eduforms de los moderadores
+    //The fee managers fund the pool that pays the storage of the moderators' eduforms
+    #[payable]
+    pub fn fund_form_storage_pool(&mut self) {
+        require!(self.has_role(Role::FeeManager, env::predecessor_account_id()), "Only fee managers can fund the form storage pool");
+        self.form_storage_pool += env::attached_deposit();
+    }
+
+    pub fn get_form_storage_pool(&self) -> U128 {
+        U128(self.form_storage_pool)
+    }
+
+    //Storage bloqueado por las eduforms de una cuenta
+    //Storage locked by the eduforms of an account
+    pub fn get_form_storage_locked(&self, account_id: AccountId) -> U128 {
+        U128(self.form_storage_locked.get(&account_id).unwrap_or(0))
+    }
+}
+
+impl Contract {
+    //Cobra (o libera) el storage usado por la eduform desde initial_storage_usage a quien la paga
+    //Charge (or release) the storage used by the eduform since initial_storage_usage to whoever pays it
+    pub fn internal_charge_form_storage(&mut self, form_id: FormId, initial_storage_usage: u64) {
+        let mut state = self.form_states.get(&form_id).expect("Form not found");
+        let storage_usage = env::storage_usage();
+
+        if storage_usage >= initial_storage_usage {
+            let bytes = storage_usage - initial_storage_usage;
+            let cost = Balance::from(bytes) * STORAGE_PRICE_PER_BYTE;
+            if state.paid_by_pool {
+                require!(self.form_storage_pool >= cost, format!("The form storage pool can't cover {} yoctoNEAR", cost));
+                self.form_storage_pool -= cost;
+            } else {
+                self.internal_lock_account_storage(&state.submitter, cost);
+            }
+            state.storage_used += bytes;
+        } else {
+            let bytes = (initial_storage_usage - storage_usage).min(state.storage_used);
+            self.internal_release_form_storage(&state, bytes);
+            state.storage_used -= bytes;
+        }
+        self.form_states.insert(&form_id, &state);
+    }
+
+    //Bloquea cost del deposito de la cuenta, que debe cubrir sus ventas, sus rentas y lo ya bloqueado
+    //Lock cost from the account's deposit, which must cover their sales, their rentals and what is already locked
+    pub fn internal_lock_account_storage(&mut self, account_id: &AccountId, cost: Balance) {
+        let locked = self.form_storage_locked.get(account_id).unwrap_or(0) + cost;
+        let required = (u128::from(self.get_supply_by_owner_id(account_id.clone()).0)
+            + u128::from(self.internal_rental_count(account_id)))
+            * STORAGE_PER_SALE
+            + locked;
+        let deposit = self.storage_deposits.get(account_id).unwrap_or(0);
+        require!(
+            deposit >= required,
+            format!("Storage deposit of {} is not enough, {} yoctoNEAR required", deposit, required)
+        );
+        self.form_storage_locked.insert(account_id, &locked);
+    }
+
+    //Cobra (o devuelve) al pool el storage usado desde initial_storage_usage, para datos que no son de un autor
+    //Charge (or return) to the pool the storage used since initial_storage_usage, for data that has no author
+    pub fn internal_charge_pool_storage(&mut self, initial_storage_usage: u64) {
+        let storage_usage = env::storage_usage();
+        if storage_usage >= initial_storage_usage {
+            let cost = Balance::from(storage_usage - initial_storage_usage) * STORAGE_PRICE_PER_BYTE;
+            require!(self.form_storage_pool >= cost, format!("The form storage pool can't cover {} yoctoNEAR", cost));
+            self.form_storage_pool -= cost;
+        } else {
+            self.form_storage_pool += Balance::from(initial_storage_usage - storage_usage) * STORAGE_PRICE_PER_BYTE;
+        }
+    }
+
+    //Devuelve el costo de los bytes liberados al pool o al deposito del autor
+    //Return the cost of the freed bytes to the pool or to the author's deposit
+    pub fn internal_release_form_storage(&mut self, state: &FormState, bytes: u64) {
+        let cost = Balance::from(bytes) * STORAGE_PRICE_PER_BYTE;
+        if state.paid_by_pool {
+            self.form_storage_pool += cost;
+        } else {
+            let locked = self.form_storage_locked.get(&state.submitter).unwrap_or(0).saturating_sub(cost);
+            if locked > 0 {
+                self.form_storage_locked.insert(&state.submitter, &locked);
+            } else {
+                self.form_storage_locked.remove(&state.submitter);
+            }
+        }
+    }
+
+    //Las eduforms sin estado son anteriores a la moderación, se asignan al pool
+    //Eduforms without state predate moderation, they are assigned to the pool
+    pub fn internal_form_state(&self, form_id: FormId) -> FormState {
+        self.form_states.get(&form_id).unwrap_or(FormState {
+            submitter: env::predecessor_account_id(),
+            status: FormStatus::Approved,
+            storage_used: 0,
+            paid_by_pool: true,
+            edited_by: env::predecessor_account_id(),
+            edited_at: 0,
+            removed_at: None,
+        })
+    }
+}
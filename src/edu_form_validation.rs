@@ -0,0 +1,98 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Valida los campos de una eduform antes de guardarla
+//Validate the fields of an eduform before storing it
+pub(crate) fn assert_valid_form(form: &EduForm) {
+    require!(is_valid_locale(&form.locale), "Locale must be a language code like en or es-MX");
+    assert_valid_title_and_descriptions(&form.title, &form.descriptions);
+
+    require!(is_valid_url(&form.website, &["https://", "http://"]), "Website must be an http(s) URL");
+    require!(is_valid_handle(&form.twitter, 15, &['_']), "Twitter must be a handle of up to 15 letters, digits or _");
+    require!(is_valid_handle(&form.instagram, 30, &['_', '.']), "Instagram must be a handle of up to 30 letters, digits, _ or .");
+    require!(
+        is_valid_url(&form.discord, &["https://discord.gg/", "https://discord.com/"]) || is_valid_handle(&form.discord, 32, &['_', '.']),
+        "Discord must be a discord invite URL or a handle"
+    );
+
+    require!(form.images.len() <= MAX_FORM_IMAGES, format!("No more than {} images", MAX_FORM_IMAGES));
+    for image in &form.images {
+        require!(!image.is_empty() && is_valid_url(image, &["https://", "ipfs://", "ar://"]), "Images must be https, ipfs or ar URLs");
+    }
+
+    require!(form.nft_contract_ids.len() <= MAX_FORM_NFT_CONTRACTS, format!("No more than {} NFT contracts", MAX_FORM_NFT_CONTRACTS));
+
+    if let Some(category) = &form.category {
+        require!(is_valid_tag(category), format!("Category must have up to {} letters, digits, spaces, - or _", MAX_FORM_TAG_LEN));
+    }
+    require!(form.tags.len() <= MAX_FORM_TAGS, format!("No more than {} tags", MAX_FORM_TAGS));
+    for tag in &form.tags {
+        require!(is_valid_tag(tag), format!("Tags must have up to {} letters, digits, spaces, - or _", MAX_FORM_TAG_LEN));
+    }
+
+    require!(form.translations.len() <= MAX_FORM_TRANSLATIONS, format!("No more than {} translations", MAX_FORM_TRANSLATIONS));
+    for (locale, translation) in &form.translations {
+        require!(is_valid_locale(locale), "Translation locales must be language codes like en or es-MX");
+        assert_valid_title_and_descriptions(&translation.title, &translation.descriptions);
+    }
+
+    //Limite total de la eduform serializada
+    //Total limit of the serialized eduform
+    let form_bytes = form.try_to_vec().unwrap().len();
+    require!(form_bytes <= MAX_FORM_BYTES, format!("Form exceeds {} bytes", MAX_FORM_BYTES));
+}
+
+fn assert_valid_title_and_descriptions(title: &str, descriptions: &[String]) {
+    require!(!title.trim().is_empty(), "Title can't be empty");
+    require!(title.len() <= MAX_FORM_TITLE_LEN, format!("Title exceeds {} bytes", MAX_FORM_TITLE_LEN));
+
+    require!(descriptions.len() <= MAX_FORM_DESCRIPTIONS, format!("No more than {} descriptions", MAX_FORM_DESCRIPTIONS));
+    for description in descriptions {
+        require!(description.len() <= MAX_FORM_DESCRIPTION_LEN, format!("Descriptions can't exceed {} bytes", MAX_FORM_DESCRIPTION_LEN));
+    }
+}
+
+//Codigo de idioma de 2 o 3 letras con región opcional (ej. "es", "es-MX")
+//Language code of 2 or 3 letters with optional region (e.g. "es", "es-MX")
+//map_or en vez de is_none_or para compilar con toolchains anteriores a 1.82
+//map_or instead of is_none_or to build with toolchains older than 1.82
+#[allow(clippy::unnecessary_map_or)]
+fn is_valid_locale(value: &str) -> bool {
+    let mut parts = value.split('-');
+    let language = parts.next().unwrap_or_default();
+    let region = parts.next();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && region.map_or(true, |region| (2..=3).contains(&region.len()) && region.chars().all(|c| c.is_ascii_alphanumeric()))
+        && parts.next().is_none()
+}
+
+//Un campo vacío es valido, si no debe empezar con alguno de los esquemas
+//An empty field is valid, otherwise it must start with one of the schemes
+fn is_valid_url(value: &str, schemes: &[&str]) -> bool {
+    if value.is_empty() {
+        return true;
+    }
+    value.len() <= MAX_FORM_URL_LEN
+        && !value.chars().any(char::is_whitespace)
+        && schemes.iter().any(|scheme| value.len() > scheme.len() && value.starts_with(scheme))
+}
+
+fn is_valid_tag(value: &str) -> bool {
+    !value.trim().is_empty()
+        && value.len() <= MAX_FORM_TAG_LEN
+        && value.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+//Handle con @ opcional, letras, numeros y los caracteres permitidos
+//Handle with optional @, letters, digits and the allowed characters
+fn is_valid_handle(value: &str, max_len: usize, allowed: &[char]) -> bool {
+    if value.is_empty() {
+        return true;
+    }
+    let handle = value.strip_prefix('@').unwrap_or(value);
+    !handle.is_empty()
+        && handle.len() <= max_len
+        && handle.chars().all(|c| c.is_ascii_alphanumeric() || allowed.contains(&c))
+}
@@ -0,0 +1,91 @@
+use near_sdk::PromiseResult;
+
+use crate::*;
+
+//Token que el marketplace tiene en escrow y la cuenta a la que se debe entregar
+//Token the marketplace holds in escrow and the account it must be delivered to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowTransfer {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    pub receiver_id: AccountId,
+}
+
+#[near_bindgen]
+impl Contract {
+    //Cualquiera puede reintentar la entrega de un token que quedó en el escrow, siempre va a la cuenta registrada
+    //Anyone can retry the delivery of a token left in the escrow, it always goes to the recorded account
+    pub fn claim_escrowed_token(&mut self, nft_contract_id: AccountId, token_id: TokenId) -> Promise {
+        let transfer = self
+            .escrowed_tokens
+            .get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id))
+            .expect("The token is not in the escrow");
+        self.internal_deliver_escrowed(vec![transfer], "Evie escrow claim".to_string()).unwrap()
+    }
+
+    //Las entregas correctas salen del registro, las que fallan quedan para claim_escrowed_token
+    //Successful deliveries leave the record, the failed ones stay for claim_escrowed_token
+    #[private]
+    pub fn resolve_escrow_deliveries(&mut self, transfers: Vec<EscrowTransfer>) -> bool {
+        let mut delivered = true;
+        for (index, transfer) in transfers.iter().enumerate() {
+            let success = matches!(env::promise_result(index as u64), PromiseResult::Successful(_));
+            delivered &= success;
+            if success {
+                self.escrowed_tokens.remove(&format!("{}{}{}", transfer.nft_contract_id, DELIMETER, transfer.token_id));
+            }
+            env::log_str(&format!(
+                "Escrow transfer of {} {} to {} {}",
+                transfer.nft_contract_id, transfer.token_id, transfer.receiver_id,
+                if success { "succeeded" } else { "failed, it can be claimed with claim_escrowed_token" }
+            ));
+        }
+        delivered
+    }
+
+    pub fn get_escrowed_token(&self, nft_contract_id: AccountId, token_id: TokenId) -> Option<EscrowTransfer> {
+        self.escrowed_tokens.get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id))
+    }
+
+    pub fn get_escrowed_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<EscrowTransfer> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.escrowed_tokens.values()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    //Registra las entregas y transfiere los tokens desde el escrow, None si no hay nada que entregar
+    //Record the deliveries and transfer the tokens from the escrow, None if there is nothing to deliver
+    //El registro ocupa menos que la venta o el trade que se borra, no se cobra aparte
+    //The record takes less than the sale or trade being deleted, it is not charged separately
+    pub fn internal_deliver_escrowed(&mut self, transfers: Vec<EscrowTransfer>, memo: String) -> Option<Promise> {
+        let delivery = transfers.iter()
+            .map(|transfer| {
+                self.escrowed_tokens.insert(
+                    &format!("{}{}{}", transfer.nft_contract_id, DELIMETER, transfer.token_id),
+                    transfer,
+                );
+                ext_contract::ext(transfer.nft_contract_id.clone())
+                    .with_attached_deposit(1)
+                    .with_static_gas(self.internal_gas_config(&transfer.nft_contract_id).nft_transfer())
+                    .nft_transfer(transfer.receiver_id.clone(), transfer.token_id.clone(), None, Some(memo.clone()))
+            })
+            .reduce(|transfers, transfer| transfers.and(transfer))?;
+        Some(delivery.then(ext_self::ext(env::current_account_id())
+            .with_static_gas(GAS_FOR_RESOLVE_ESCROW_DELIVERIES)
+            .resolve_escrow_deliveries(transfers)))
+    }
+
+    //Gas de las transferencias desde el escrow más su callback, con el override de cada contrato NFT
+    //Gas of the transfers from the escrow plus their callback, with the override of each NFT contract
+    pub fn internal_escrow_delivery_gas<'a>(&self, nft_contract_ids: impl Iterator<Item = &'a AccountId>) -> Gas {
+        nft_contract_ids
+            .map(|nft_contract_id| self.internal_gas_config(nft_contract_id).nft_transfer())
+            .fold(GAS_FOR_RESOLVE_ESCROW_DELIVERIES, |total, gas| total + gas)
+    }
+}
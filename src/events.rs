@@ -0,0 +1,13 @@
+use crate::*;
+
+//Eventos en formato NEP-297, los indexers los leen de los logs
+//Events in NEP-297 format, the indexers read them from the logs
+pub(crate) fn log_event<T: Serialize>(standard: &str, version: &str, event: &str, data: T) {
+    let event = near_sdk::serde_json::json!({
+        "standard": standard,
+        "version": version,
+        "event": event,
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", event));
+}
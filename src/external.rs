@@ -17,6 +17,20 @@ trait ExtContract {
         max_len_payout: u32, //Longitud máxima del payout = Max length of payout
     );
 
+    //Payout del token a un precio sin transferirlo, lo usa preview_payout
+    //Payout of the token at a price without transferring it, used by preview_payout
+    fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout;
+
+    //Transferencia simple, la usan los trades para el escrow
+    //Simple transfer, used by trades for the escrow
+    fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    );
+
     /*Tener en cuenta que esta función considera comprador a este contrato,
     por lo que enviará el nft al mismo, y no al usuario, entonces
     debemos hacer un callback a esta función para que envie
@@ -0,0 +1,119 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Gas para las llamadas a un contrato NFT, los campos en None usan las constantes por defecto
+//Gas for the calls to an NFT contract, the fields in None use the default constants
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasConfig {
+    //nft_transfer_payout, nft_transfer y nft_buy
+    //nft_transfer_payout, nft_transfer and nft_buy
+    pub nft_transfer: Option<U64>,
+    //resolve_purchase, reparte el payout entre las cuentas
+    //resolve_purchase, splits the payout among the accounts
+    pub royalties: Option<U64>,
+    //Views de ext_paras y nft_approve
+    //ext_paras views and nft_approve
+    pub cross_contract_call: Option<U64>,
+}
+
+impl GasConfig {
+    pub fn nft_transfer(&self) -> Gas {
+        self.nft_transfer.map(|gas| Gas(gas.0)).unwrap_or(GAS_FOR_NFT_TRANSFER)
+    }
+
+    pub fn royalties(&self) -> Gas {
+        self.royalties.map(|gas| Gas(gas.0)).unwrap_or(GAS_FOR_ROYALTIES)
+    }
+
+    pub fn cross_contract_call(&self) -> Gas {
+        self.cross_contract_call.map(|gas| Gas(gas.0)).unwrap_or(GAS_FOR_CROSS_CONTRACT_CALL)
+    }
+}
+
+//Override guardado junto al admin que pagó su storage, a quien se le devuelve al borrarlo o reemplazarlo
+//Override stored next to the admin that paid its storage, who gets it back when it is removed or replaced
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StoredGasConfig {
+    pub config: GasConfig,
+    pub payer_id: AccountId,
+    pub storage_deposit: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasConfigWithContractId {
+    pub nft_contract_id: AccountId,
+    pub config: GasConfig,
+    pub payer_id: AccountId,
+    pub storage_deposit: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    //Añade o actualiza el gas de un contrato NFT, el storage lo paga el deposito adjunto y el exceso se devuelve
+    //Add or update the gas of an NFT contract, the attached deposit pays the storage and the excess is refunded
+    //Si reemplaza un override, el admin que pagó el anterior recupera su storage
+    //If it replaces an override, the admin that paid the previous one gets its storage back
+    #[payable]
+    pub fn set_gas_config(&mut self, nft_contract_id: AccountId, config: GasConfig) {
+        let account_id = env::predecessor_account_id();
+        require!(self.has_role(Role::Admin, account_id.clone()), "Only admins can set gas configs");
+        for gas in [config.nft_transfer, config.royalties, config.cross_contract_call].iter().flatten() {
+            require!(gas.0 > 0 && gas.0 <= MAX_GAS_OVERRIDE, format!("Gas overrides must be between 1 and {}", MAX_GAS_OVERRIDE));
+        }
+        let previous = self.gas_configs.remove(&nft_contract_id);
+        let initial_storage_usage = env::storage_usage();
+        let mut stored = StoredGasConfig { config, payer_id: account_id.clone(), storage_deposit: U128(0) };
+        self.gas_configs.insert(&nft_contract_id, &stored);
+        let cost = Balance::from(env::storage_usage() - initial_storage_usage) * STORAGE_PRICE_PER_BYTE;
+        let deposit = env::attached_deposit();
+        require!(deposit >= cost, format!("Attach at least {} yoctoNEAR for the gas config storage", cost));
+        stored.storage_deposit = U128(cost);
+        self.gas_configs.insert(&nft_contract_id, &stored);
+        if deposit > cost {
+            Promise::new(account_id).transfer(deposit - cost);
+        }
+        if let Some(previous) = previous {
+            Promise::new(previous.payer_id).transfer(previous.storage_deposit.0);
+        }
+    }
+
+    //El storage se devuelve al admin que lo pagó, no al que la borra
+    //The storage is refunded to the admin that paid it, not to the one removing it
+    #[payable]
+    pub fn remove_gas_config(&mut self, nft_contract_id: AccountId) {
+        assert_one_yocto();
+        require!(self.has_role(Role::Admin, env::predecessor_account_id()), "Only admins can remove gas configs");
+        let stored = self.gas_configs.remove(&nft_contract_id).expect("Gas config not found");
+        Promise::new(stored.payer_id).transfer(stored.storage_deposit.0);
+    }
+
+    pub fn get_gas_config(&self, nft_contract_id: AccountId) -> Option<GasConfig> {
+        self.gas_configs.get(&nft_contract_id).map(|stored| stored.config)
+    }
+
+    pub fn get_gas_configs(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<GasConfigWithContractId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.gas_configs.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(nft_contract_id, stored)| GasConfigWithContractId {
+                nft_contract_id,
+                config: stored.config,
+                payer_id: stored.payer_id,
+                storage_deposit: stored.storage_deposit,
+            })
+            .collect()
+    }
+}
+
+impl Contract {
+    //Gas efectivo para un contrato NFT, sin override son las constantes
+    //Effective gas for an NFT contract, without an override they are the constants
+    pub fn internal_gas_config(&self, nft_contract_id: &AccountId) -> GasConfig {
+        self.gas_configs.get(nft_contract_id).map(|stored| stored.config).unwrap_or_default()
+    }
+}
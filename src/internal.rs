@@ -5,7 +5,11 @@ use crate::*;
 pub struct Token {
     pub owner_id: AccountId,
     pub approved_account_ids: HashMap<AccountId, u64>,
+    //No todos los contratos NEP-171 los retornan en nft_token
+    //Not every NEP-171 contract returns them in nft_token
+    #[serde(default)]
     pub next_approval_id: u64,
+    #[serde(default)]
     pub royalty: HashMap<AccountId, u32>,
 }
 
@@ -77,3 +81,30 @@ impl Contract {
         sale
     }
 }
+
+impl Contract {
+    //Como internal_remove_sale pero sin panic, para los callbacks
+    //Like internal_remove_sale but without panics, for the callbacks
+    pub fn internal_try_remove_sale(&mut self, contract_and_token_id: &ContractAndTokenId) -> Option<Sale> {
+        let sale = self.sales.remove(contract_and_token_id)?;
+        if let Some(mut by_owner_id) = self.by_owner_id.get(&sale.owner_id) {
+            by_owner_id.remove(contract_and_token_id);
+            if by_owner_id.is_empty() {
+                self.by_owner_id.remove(&sale.owner_id);
+            } else {
+                self.by_owner_id.insert(&sale.owner_id, &by_owner_id);
+            }
+        }
+        if let Ok(nft_contract_id) = sale.nft_contract_id.parse::<AccountId>() {
+            if let Some(mut by_nft_contract_id) = self.by_nft_contract_id.get(&nft_contract_id) {
+                by_nft_contract_id.remove(&sale.token_id);
+                if by_nft_contract_id.is_empty() {
+                    self.by_nft_contract_id.remove(&nft_contract_id);
+                } else {
+                    self.by_nft_contract_id.insert(&nft_contract_id, &by_nft_contract_id);
+                }
+            }
+        }
+        Some(sale)
+    }
+}
@@ -1,7 +1,7 @@
 //#![deny(warnings)] // deny warnings in the code
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
-use near_sdk::json_types::{U128, U64};
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     assert_one_yocto, env, ext_contract, near_bindgen, promise_result_as_success, AccountId,
@@ -13,21 +13,57 @@ use crate::cross_contract_calls::*;
 use crate::external::*;
 use crate::internal::*;
 use crate::sale::*;
+use crate::edu_form_quizzes::*;
+use crate::credentials::*;
+use crate::drops::*;
+use crate::allowlists::*;
+use crate::bundles::*;
+use crate::trades::*;
+use crate::escrow::*;
+use crate::rentals::*;
+use crate::events::*;
 use crate::admin_functions::*;
+use crate::proposals::*;
+use crate::pause::*;
+use crate::payout_policy::*;
+use crate::gas_config::*;
+use crate::edu_form_validation::*;
 //use crate::cart_functions::*;
 use crate::buy_from_other_marketplaces::*;
 
 
 use near_sdk::env::STORAGE_PRICE_PER_BYTE;
 
+mod allowlists;
+mod bundles;
 mod cart_functions;
+mod collection_functions;
+mod credentials;
 mod cross_contract_calls;
+mod drops;
 mod edu_form_functions;
+mod edu_form_history;
+mod edu_form_moderation;
+mod edu_form_quizzes;
+mod edu_form_search;
+mod edu_form_storage;
+mod edu_form_validation;
+mod escrow;
+mod events;
 mod external;
+mod gas_config;
 mod internal;
+mod migrations;
 mod nft_callbacks;
+mod pause;
+mod payout_policy;
+mod proposals;
+mod rentals;
 mod sale;
 mod sale_views;
+mod trades;
+#[cfg(test)]
+mod testing;
 mod buy_from_other_marketplaces;
 mod admin_functions;
 
@@ -39,11 +75,80 @@ const GAS_FOR_NFT_TRANSFER_AS_NUMBER: u128 = 15_000_000_000_000;
 const STORAGE_PRICE_PER_BYTE_AS_NUMBER: u128 = 1_000_000_000_000;
 const ONE_NEAR : u128 = 1_000_000_000_000_000_000_000_000;
 const GAS_FOR_ROYALTIES: Gas = Gas(115_000_000_000_000);
+const GAS_FOR_DROP_MINT: Gas = Gas(50_000_000_000_000);
+const GAS_FOR_RESOLVE_DROP_MINT: Gas = Gas(15_000_000_000_000);
+const GAS_FOR_RESOLVE_BUNDLE_PURCHASE: Gas = Gas(50_000_000_000_000);
+//Gas máximo que puede reservar la compra de un bundle (escrow, callback y entregas), el resto de los 300 TGas es para offer_bundle
+//Maximum gas a bundle purchase can reserve (escrow, callback and deliveries), the rest of the 300 TGas is for offer_bundle
+const MAX_BUNDLE_PURCHASE_GAS: Gas = Gas(270_000_000_000_000);
+//resolve_trade_escrow sin contar las entregas desde el escrow que inicia
+//resolve_trade_escrow without counting the deliveries from the escrow it starts
+const GAS_FOR_RESOLVE_TRADE_ESCROW: Gas = Gas(20_000_000_000_000);
+const GAS_FOR_RESOLVE_ESCROW_DELIVERIES: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_RENT: Gas = Gas(15_000_000_000_000);
+const GAS_FOR_NFT_PAYOUT: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_PREVIEW_PAYOUT: Gas = Gas(10_000_000_000_000);
+//resolve_purchase_payout sin contar la transferencia y resolve_purchase que inicia
+//resolve_purchase_payout without counting the transfer and the resolve_purchase it starts
+const GAS_FOR_RESOLVE_PURCHASE_PAYOUT: Gas = Gas(15_000_000_000_000);
+//migrate reescribe las ventas y eduforms de layouts anteriores
+//migrate rewrites the sales and eduforms of previous layouts
+const GAS_FOR_MIGRATE: Gas = Gas(200_000_000_000_000);
+//Máximo para los overrides de gas de un contrato NFT, una compra encadena varias llamadas dentro de los 300 TGas
+//Maximum for the gas overrides of an NFT contract, a purchase chains several calls within the 300 TGas
+const MAX_GAS_OVERRIDE: u64 = 100_000_000_000_000;
 const NO_DEPOSIT: Balance = 0;
 const MAX_ROYALTIES_ACCOUNTS: u8 = 10;
+//Política de payout inicial: royalties de hasta 50% y el vendedor recibe al menos 50%
+//Initial payout policy: royalties up to 50% and the seller gets at least 50%
+const DEFAULT_MAX_ROYALTY_BPS: u32 = 5_000;
+const DEFAULT_MIN_SELLER_BPS: u32 = 5_000;
 
 const STORAGE_PER_SALE: u128 = 1000 * STORAGE_PRICE_PER_BYTE;
 
+//Limites de tamaño de las eduforms
+//Size limits of the eduforms
+const MAX_FORM_BYTES: usize = 16 * 1024;
+const MAX_FORM_TITLE_LEN: usize = 128;
+const MAX_FORM_URL_LEN: usize = 256;
+const MAX_FORM_DESCRIPTION_LEN: usize = 2048;
+const MAX_FORM_DESCRIPTIONS: usize = 20;
+const MAX_FORM_IMAGES: usize = 20;
+const MAX_FORM_NFT_CONTRACTS: usize = 10;
+const MAX_FORM_TAGS: usize = 10;
+const MAX_FORM_TAG_LEN: usize = 32;
+const MAX_FORM_TRANSLATIONS: usize = 5;
+const MAX_QUIZ_QUESTIONS: usize = 20;
+//Cada venta del bundle es una llamada nft_transfer_payout al escrow y una entrega, el gas limita la cantidad
+//Each sale of the bundle is an nft_transfer_payout call to the escrow and a delivery, gas limits the amount
+const MAX_BUNDLE_ITEMS: usize = 5;
+//Tokens de ambos lados de un trade, cada uno se transfiere dos veces (escrow y entrega)
+//Tokens of both sides of a trade, each one is transferred twice (escrow and delivery)
+const MAX_TRADE_TOKENS: usize = 4;
+//Tiempo tras accept_trade para que cualquiera pueda cerrar un trade que quedó en Settling (1 hora)
+//Time after accept_trade for anyone to close a trade that stayed Settling (1 hour)
+const TRADE_SETTLE_TIMEOUT: u64 = 60 * 60 * 1_000_000_000;
+
+//Aprobaciones mínimas de las propuestas de admins, una cuenta sola nunca ejecuta una propuesta
+//Minimum approvals of the admin proposals, a single account never executes a proposal
+const MIN_PROPOSAL_THRESHOLD: u32 = 2;
+//Eduforms que se borran por llamada de RemoveAllForms
+//Eduforms deleted per RemoveAllForms call
+const REMOVE_ALL_FORMS_BATCH: u64 = 50;
+//Bytes que ocupa approved_at al pasar de None a Some
+//Bytes approved_at takes when it goes from None to Some
+const APPROVED_AT_STORAGE: u64 = 8;
+//Timelock inicial de las propuestas de admins (24 horas en nanosegundos)
+//Initial timelock of the admin proposals (24 hours in nanoseconds)
+const DEFAULT_PROPOSAL_TIMELOCK: u64 = 24 * 60 * 60 * 1_000_000_000;
+//Un dia de renta en nanosegundos
+//One rental day in nanoseconds
+const RENTAL_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+//Versión del layout del estado, se guarda aparte de STATE para que migrate sepa como leerlo
+//Version of the state layout, it is stored apart from STATE so migrate knows how to read it
+const STATE_VERSION: u8 = 2;
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
 //TODO: Change Typo DELIMETER to DELIMITER
 static DELIMETER: &str = ".";
 
@@ -54,6 +159,10 @@ pub type TokenId = String;
 pub type FungibleTokenId = AccountId;
 pub type ContractAndTokenId = String;
 pub type FormId = u32;
+pub type AllowlistId = u64;
+pub type BundleId = u64;
+pub type TradeId = u64;
+pub type ProposalId = u64;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
 #[serde(crate = "near_sdk::serde")]
@@ -65,6 +174,10 @@ pub struct CartItem {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct EduForm {
+    //Idioma de title y descriptions, se usa cuando no hay traducción para el idioma pedido
+    //Language of title and descriptions, used when there is no translation for the requested language
+    #[serde(default = "default_locale")]
+    pub locale: String,
     pub title: String,
     pub supply: U64,
     pub website: String,
@@ -72,10 +185,75 @@ pub struct EduForm {
     pub discord: String,
     pub instagram: String,
     pub descriptions: Vec<String>,
+    //Contratos NFT de las colecciones que describe esta eduform
+    //NFT contracts of the collections this eduform describes
+    #[serde(default)]
+    pub nft_contract_ids: Vec<AccountId>,
+    //Categoría y tags para navegar las eduforms
+    //Category and tags to browse the eduforms
+    pub category: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
     // pub price: SalePriceInYoctoNear,
     // pub token_id: TokenId,
     // pub contract_id: AccountId,
     pub images: Vec<String>,
+    //Traducciones de title y descriptions por idioma (ej. "es", "en")
+    //Translations of title and descriptions by language (e.g. "es", "en")
+    #[serde(default)]
+    pub translations: HashMap<String, FormTranslation>,
+}
+
+//Las eduforms enviadas sin idioma se asumen en inglés, como las migradas
+//Eduforms sent without a language are assumed to be in English, like the migrated ones
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FormTranslation {
+    pub title: String,
+    pub descriptions: Vec<String>,
+}
+
+//Modo en el que el marketplace acepta contratos NFT para listar
+//Mode in which the marketplace accepts NFT contracts for listing
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CollectionMode {
+    //Cualquier contrato puede listar, el registro solo aporta metadata
+    //Any contract can list, the registry only provides metadata
+    Open,
+    //Solo los contratos permitidos en el registro pueden listar
+    //Only the contracts allowed in the registry can list
+    Allowlist,
+    //Cualquier contrato puede listar excepto los denegados en el registro
+    //Any contract can list except the ones denied in the registry
+    Denylist,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum CollectionStatus {
+    Allowed,
+    Denied,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Collection {
+    pub status: CollectionStatus,
+    pub verified: bool,
+    pub name: String,
+    pub edu_form_id: Option<FormId>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CollectionWithContractId {
+    pub nft_contract_id: AccountId,
+    pub collection: Collection,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
@@ -84,6 +262,52 @@ pub struct EduFormWithId {
     pub id: FormId,
     // pub visibility: bool,
     pub form: EduForm,
+    pub status: FormStatus,
+    pub submitter: Option<AccountId>,
+    pub removed_at: Option<U64>,
+}
+
+//Estado de moderación de una eduform
+//Moderation status of an eduform
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum FormStatus {
+    Pending,
+    Approved,
+    Rejected { reason: String },
+    ChangesRequested { reason: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FormState {
+    //Cuenta que envió o añadió la eduform
+    //Account that submitted or added the eduform
+    pub submitter: AccountId,
+    pub status: FormStatus,
+    //Bytes de storage que ocupa la eduform
+    //Storage bytes used by the eduform
+    pub storage_used: u64,
+    //Si es true el storage lo paga el pool del marketplace, si no el deposito del autor
+    //If true the storage is paid by the marketplace pool, otherwise by the author's deposit
+    pub paid_by_pool: bool,
+    //Cuenta y timestamp de la ultima edición
+    //Account and timestamp of the last edit
+    pub edited_by: AccountId,
+    pub edited_at: u64,
+    //Timestamp del borrado, las eduforms borradas se pueden restaurar
+    //Timestamp of the removal, removed eduforms can be restored
+    pub removed_at: Option<u64>,
+}
+
+//Versión anterior de una eduform, con quien la escribió y cuando
+//Previous version of an eduform, with who wrote it and when
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FormVersion {
+    pub form: EduForm,
+    pub editor: AccountId,
+    pub timestamp: U64,
 }
 
 // #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
@@ -113,9 +337,33 @@ pub struct Payout {
 pub struct Contract {
     //dueño del contrato
     //owner of the contract
-    pub owner_id: AccountId,
     pub owner: AccountId,
-    pub admins: UnorderedSet<AccountId>,
+    //Cambio de owner pendiente de aceptación
+    //Owner change pending acceptance
+    pub ownership_proposal: Option<OwnershipProposal>,
+    //Cuentas con cada rol
+    //Accounts with each role
+    pub role_members: LookupMap<Role, UnorderedSet<AccountId>>,
+    //Propuestas de admins pendientes y su configuración multisig
+    //Pending admin proposals and their multisig configuration
+    pub proposals: UnorderedMap<ProposalId, Proposal>,
+    pub next_proposal_id: ProposalId,
+    pub proposal_config: ProposalConfig,
+    //Una propuesta RemoveAllForms ejecutada que sigue borrando por lotes
+    //An executed RemoveAllForms proposal that is still deleting in batches
+    pub remove_all_forms_in_progress: bool,
+    //sha256 del wasm aprobado con una propuesta UpgradeContract, upgrade solo despliega ese código
+    //sha256 of the wasm approved by an UpgradeContract proposal, upgrade only deploys that code
+    pub approved_upgrade_hash: Option<CryptoHash>,
+    //Partes del marketplace pausadas por los pausers
+    //Parts of the marketplace paused by the pausers
+    pub paused_scopes: Vec<PauseScope>,
+    //Límites de royalties y parte minima del vendedor en los payouts
+    //Royalty limits and minimum seller share in the payouts
+    pub payout_policy: PayoutPolicy,
+    //Overrides de gas por contrato NFT, editados por los admins
+    //Gas overrides per NFT contract, edited by the admins
+    pub gas_configs: UnorderedMap<AccountId, StoredGasConfig>,
     //Para mantener una lista de cada venta mapearemos el ContractAndTokenId a una venta
     //In order to maintain a list of every sale, well map the ContractAndTokenId to the sale
     //Este está hecho de `contract ID + DELIMETER + token ID`
@@ -139,7 +387,85 @@ pub struct Contract {
 
     pub edu_form_number: u32,
 
-    pub admin_can_add_admins: bool,
+    //Indice de la eduform de cada contrato NFT
+    //Index of the eduform of each NFT contract
+    pub form_by_nft_contract_id: LookupMap<AccountId, FormId>,
+
+    //Estado de moderación y autor de cada eduform
+    //Moderation status and author of each eduform
+    pub form_states: LookupMap<FormId, FormState>,
+
+    //Cola de eduforms pendientes de moderación
+    //Queue of eduforms pending moderation
+    pub pending_forms: UnorderedSet<FormId>,
+
+    //Storage de eduforms bloqueado en el deposito de cada autor
+    //Eduform storage locked in the deposit of each author
+    pub form_storage_locked: LookupMap<AccountId, Balance>,
+
+    //Pool financiado por los fee managers para pagar el storage de las eduforms de moderadores
+    //Pool funded by the fee managers to pay the storage of the moderators' eduforms
+    pub form_storage_pool: Balance,
+
+    //Historial de versiones de cada eduform
+    //Version history of each eduform
+    pub form_history: LookupMap<FormId, Vector<FormVersion>>,
+
+    //Indices de busqueda de eduforms por tag, categoría y titulo (en minusculas)
+    //Search indexes of eduforms by tag, category and title (lowercase)
+    pub forms_by_tag: LookupMap<String, UnorderedSet<FormId>>,
+    pub forms_by_category: LookupMap<String, UnorderedSet<FormId>>,
+    //La llave es `titulo + DELIMETER + form ID`
+    //The key is `title + DELIMETER + form ID`
+    pub forms_by_title: TreeMap<String, FormId>,
+
+    //Quiz de cada eduform y cuentas que lo completaron
+    //Quiz of each eduform and accounts that completed it
+    pub form_quizzes: LookupMap<FormId, Quiz>,
+    pub form_completions: LookupMap<FormId, UnorderedSet<AccountId>>,
+    pub completed_forms: LookupMap<AccountId, UnorderedSet<FormId>>,
+
+    //Credenciales soulbound emitidas al completar eduforms
+    //Soulbound credentials issued when completing eduforms
+    pub credentials: UnorderedMap<TokenId, Credential>,
+    pub credentials_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
+
+    //Ventas primarias (drops) de cada eduform
+    //Primary sales (drops) of each eduform
+    pub drops: LookupMap<FormId, FormDrop>,
+    //Mints por wallet de cada drop, la llave es `form ID + DELIMETER + account ID`
+    //Mints per wallet of each drop, the key is `form ID + DELIMETER + account ID`
+    pub drop_mints: LookupMap<String, u32>,
+
+    //Allowlists para drops y ventas privadas
+    //Allowlists for drops and private sales
+    pub allowlists: LookupMap<AllowlistId, Allowlist>,
+    pub allowlist_accounts: LookupMap<AllowlistId, UnorderedSet<AccountId>>,
+    pub next_allowlist_id: AllowlistId,
+
+    //Bundles de ventas que se compran juntas
+    //Bundles of sales bought together
+    pub bundles: UnorderedMap<BundleId, Bundle>,
+    pub next_bundle_id: BundleId,
+
+    //Trades de NFTs entre cuentas
+    //NFT trades between accounts
+    pub trades: UnorderedMap<TradeId, Trade>,
+    pub next_trade_id: TradeId,
+    //Tokens en escrow que quedan por entregar (trades y bundles)
+    //Tokens in escrow still to be delivered (trades and bundles)
+    pub escrowed_tokens: UnorderedMap<ContractAndTokenId, EscrowTransfer>,
+
+    //NFTs en renta, la llave es `contract + DELIMETER + token ID`
+    //NFTs for rent, the key is `contract + DELIMETER + token ID`
+    pub rentals: UnorderedMap<ContractAndTokenId, Rental>,
+    pub rentals_by_owner_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+
+    //Registro de colecciones NFT curadas por los collection curators
+    //Registry of NFT collections curated by the collection curators
+    pub collections: UnorderedMap<AccountId, Collection>,
+
+    pub collection_mode: CollectionMode,
 }
 
 #[derive(BorshStorageKey, BorshSerialize)]
@@ -157,22 +483,71 @@ pub enum StorageKey {
     CartInner { account_id_hash: CryptoHash },
     EduForms,
     EduFormsInner { account_id_hash: CryptoHash },
-    Admins,
+    RoleMembers,
+    RoleMembersInner { role: Role },
+    Proposals,
+    Collections,
+    FormByNFTContractId,
+    FormStates,
+    PendingForms,
+    FormStorageLocked,
+    FormHistory,
+    FormHistoryInner { form_id: FormId },
+    FormsByTag,
+    FormsByTagInner { tag_hash: CryptoHash },
+    FormsByCategory,
+    FormsByCategoryInner { category_hash: CryptoHash },
+    FormsByTitle,
+    FormQuizzes,
+    FormCompletions,
+    FormCompletionsInner { form_id: FormId },
+    CompletedForms,
+    CompletedFormsInner { account_id_hash: CryptoHash },
+    Credentials,
+    CredentialsPerOwner,
+    CredentialsPerOwnerInner { account_id_hash: CryptoHash },
+    Drops,
+    DropMints,
+    Allowlists,
+    AllowlistAccounts,
+    AllowlistAccountsInner { allowlist_id: AllowlistId },
+    Bundles,
+    Trades,
+    Rentals,
+    RentalsByOwnerId,
+    RentalsByOwnerIdInner { account_id_hash: CryptoHash },
+    GasConfigs,
+    EscrowedTokens,
 }
 
 #[near_bindgen]
 impl Contract {
     //Función inicial, solo se ejecuta una vez
     //Init function, just call it once
+    //admins son los primeros admins, después el rol Admin solo cambia con propuestas
+    //admins are the first admins, afterwards the Admin role only changes through proposals
     #[init]
-    pub fn new(owner: AccountId) -> Self {
-        let owner_id = env::signer_account_id();
-        let this = Self {
-            owner_id,
+    pub fn new(owner: AccountId, admins: Option<Vec<AccountId>>) -> Self {
+        let mut this = Self {
             owner,
+            ownership_proposal: None,
             //Para evitar colisión de datos
             //Avoiding data collisions
-            admins: UnorderedSet::new(StorageKey::Admins),
+            role_members: LookupMap::new(StorageKey::RoleMembers),
+            proposals: UnorderedMap::new(StorageKey::Proposals),
+            next_proposal_id: 0,
+            proposal_config: ProposalConfig {
+                threshold: MIN_PROPOSAL_THRESHOLD,
+                timelock: U64(DEFAULT_PROPOSAL_TIMELOCK),
+            },
+            remove_all_forms_in_progress: false,
+            approved_upgrade_hash: None,
+            paused_scopes: Vec::new(),
+            gas_configs: UnorderedMap::new(StorageKey::GasConfigs),
+            payout_policy: PayoutPolicy {
+                max_royalty_bps: DEFAULT_MAX_ROYALTY_BPS,
+                min_seller_bps: DEFAULT_MIN_SELLER_BPS,
+            },
             sales: UnorderedMap::new(StorageKey::Sales),
             by_owner_id: LookupMap::new(StorageKey::ByOwnerId),
             by_nft_contract_id: LookupMap::new(StorageKey::ByNFTContractId),
@@ -180,8 +555,39 @@ impl Contract {
             cart: UnorderedMap::new(StorageKey::Cart),
             edu_forms: UnorderedMap::new(StorageKey::EduForms),
             edu_form_number: 0,
-            admin_can_add_admins: false,
+            form_by_nft_contract_id: LookupMap::new(StorageKey::FormByNFTContractId),
+            form_states: LookupMap::new(StorageKey::FormStates),
+            pending_forms: UnorderedSet::new(StorageKey::PendingForms),
+            form_storage_locked: LookupMap::new(StorageKey::FormStorageLocked),
+            form_storage_pool: 0,
+            form_history: LookupMap::new(StorageKey::FormHistory),
+            forms_by_tag: LookupMap::new(StorageKey::FormsByTag),
+            forms_by_category: LookupMap::new(StorageKey::FormsByCategory),
+            forms_by_title: TreeMap::new(StorageKey::FormsByTitle),
+            form_quizzes: LookupMap::new(StorageKey::FormQuizzes),
+            form_completions: LookupMap::new(StorageKey::FormCompletions),
+            completed_forms: LookupMap::new(StorageKey::CompletedForms),
+            credentials: UnorderedMap::new(StorageKey::Credentials),
+            credentials_per_owner: LookupMap::new(StorageKey::CredentialsPerOwner),
+            drops: LookupMap::new(StorageKey::Drops),
+            drop_mints: LookupMap::new(StorageKey::DropMints),
+            allowlists: LookupMap::new(StorageKey::Allowlists),
+            allowlist_accounts: LookupMap::new(StorageKey::AllowlistAccounts),
+            next_allowlist_id: 0,
+            bundles: UnorderedMap::new(StorageKey::Bundles),
+            next_bundle_id: 0,
+            trades: UnorderedMap::new(StorageKey::Trades),
+            next_trade_id: 0,
+            escrowed_tokens: UnorderedMap::new(StorageKey::EscrowedTokens),
+            rentals: UnorderedMap::new(StorageKey::Rentals),
+            rentals_by_owner_id: LookupMap::new(StorageKey::RentalsByOwnerId),
+            collections: UnorderedMap::new(StorageKey::Collections),
+            collection_mode: CollectionMode::Open,
         };
+        for admin in admins.unwrap_or_default() {
+            this.internal_grant_role(Role::Admin, admin);
+        }
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
         this
     }
 
@@ -243,9 +649,11 @@ impl Contract {
         //Obtenemos el length de las ventas
         //Get the length of the sales
         let len = sales.map(|s| s.len()).unwrap_or_default();
-        //¿Cuanto NEAR está siendo usado por esas ventas?
-        //How much NEAR is being used by those sales?
-        let diff = u128::from(len) * STORAGE_PER_SALE;
+        //¿Cuanto NEAR está siendo usado por esas ventas, sus rentas y sus eduforms?
+        //How much NEAR is being used by those sales, their rentals and their eduforms?
+        let diff = u128::from(len + self.internal_rental_count(&owner_id)) * STORAGE_PER_SALE
+            + self.form_storage_locked.get(&owner_id).unwrap_or(0);
+        assert!(amount >= diff, "Storage deposit is below the storage in use");
         //Obtenemos el exceso de storage depositado
         //Get the excess storage deposit
         amount -= diff;
@@ -284,21 +692,24 @@ impl Contract {
     // //Obtener la cantidad total de NFTs minteados del contrato
     // //Get the total amount of NFTs minted by the contract
     pub fn nft_total_supply_marketplace(&self, marketplace_contract_id: AccountId) -> Promise {
-        ext_paras::ext(marketplace_contract_id).with_static_gas(GAS_FOR_CROSS_CONTRACT_CALL).nft_total_supply()
+        let gas = self.internal_gas_config(&marketplace_contract_id).cross_contract_call();
+        ext_paras::ext(marketplace_contract_id).with_static_gas(gas).nft_total_supply()
         .then(ext_nft_dos::ext(env::current_account_id()).with_static_gas(GAS_FOR_CROSS_CONTRACT_CALL).on_nft_total_supply())
     }
 
     // //Obtener los tokens de un usuario
     // //Get the tokens of a user
-
-    pub fn nft_tokens_for_owner(
+    //nft_tokens_for_owner con marketplace_contract_id sigue llamando aquí, sin él lista las credenciales (NEP-171)
+    //nft_tokens_for_owner with marketplace_contract_id still calls here, without it it lists the credentials (NEP-171)
+    pub fn nft_tokens_for_owner_marketplace(
         &self,
         account_id: AccountId,
         from_index: Option<U128>,
         limit: Option<u64>,
         marketplace_contract_id: AccountId,
     ) -> Promise {
-        ext_paras::ext(marketplace_contract_id).with_static_gas(GAS_FOR_CROSS_CONTRACT_CALL).nft_tokens_for_owner(account_id, from_index, limit)
+        let gas = self.internal_gas_config(&marketplace_contract_id).cross_contract_call();
+        ext_paras::ext(marketplace_contract_id).with_static_gas(gas).nft_tokens_for_owner(account_id, from_index, limit)
         .then(ext_nft_dos::ext(env::current_account_id()).with_static_gas(GAS_FOR_CROSS_CONTRACT_CALL).on_nft_tokens_for_owner())
     }
 
@@ -310,8 +721,9 @@ impl Contract {
         msg: Option<String>,
         marketplace_contract_id: AccountId,
     ) {
+        let gas = self.internal_gas_config(&marketplace_contract_id).cross_contract_call();
         ext_paras::ext(marketplace_contract_id).with_attached_deposit(1)
-        .with_static_gas(GAS_FOR_CROSS_CONTRACT_CALL).nft_approve(token_id, env::current_account_id(), msg);
+        .with_static_gas(gas).nft_approve(token_id, env::current_account_id(), msg);
     }
 
     //Callback de Funciones externas
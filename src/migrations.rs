@@ -0,0 +1,220 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Layouts anteriores del estado, solo se leen durante migrate
+//Previous state layouts, they are only read during migrate
+
+//Layout de la primera versión desplegada del contrato
+//Layout of the first deployed version of the contract
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV1 {
+    pub owner_id: AccountId,
+    pub owner: AccountId,
+    pub admins: UnorderedSet<AccountId>,
+    pub sales: UnorderedMap<ContractAndTokenId, SaleV1>,
+    pub by_owner_id: LookupMap<AccountId, UnorderedSet<ContractAndTokenId>>,
+    pub by_nft_contract_id: LookupMap<AccountId, UnorderedSet<TokenId>>,
+    pub storage_deposits: LookupMap<AccountId, Balance>,
+    pub cart: UnorderedMap<AccountId, Vec<CartItem>>,
+    pub edu_forms: UnorderedMap<FormId, EduFormV1>,
+    pub edu_form_number: u32,
+    pub admin_can_add_admins: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SaleV1 {
+    pub owner_id: AccountId,
+    pub approval_id: u64,
+    pub nft_contract_id: String,
+    pub token_id: String,
+    pub sale_conditions: SalePriceInYoctoNear,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EduFormV1 {
+    pub title: String,
+    pub supply: U64,
+    pub website: String,
+    pub twitter: String,
+    pub discord: String,
+    pub instagram: String,
+    pub descriptions: Vec<String>,
+    pub images: Vec<String>,
+}
+
+//Estado leído según la versión guardada en STATE_VERSION_KEY (sin versión es V1)
+//State read according to the version stored in STATE_VERSION_KEY (no version is V1)
+pub enum VersionedContract {
+    V1(Box<ContractV1>),
+    V2(Box<Contract>),
+}
+
+impl VersionedContract {
+    pub fn read() -> Self {
+        let state = env::storage_read(b"STATE").expect("The contract is not initialized");
+        let version = env::storage_read(STATE_VERSION_KEY).map(|version| version[0]).unwrap_or(1);
+        match version {
+            1 => VersionedContract::V1(Box::new(ContractV1::try_from_slice(&state).expect("The state is not a V1 layout"))),
+            2 => VersionedContract::V2(Box::new(Contract::try_from_slice(&state).expect("The state is not a V2 layout"))),
+            _ => env::panic_str(&format!("Unknown state version {}", version)),
+        }
+    }
+}
+
+impl From<SaleV1> for Sale {
+    fn from(sale: SaleV1) -> Self {
+        Sale {
+            owner_id: sale.owner_id,
+            approval_id: sale.approval_id,
+            nft_contract_id: sale.nft_contract_id,
+            token_id: sale.token_id,
+            sale_conditions: sale.sale_conditions,
+            allowlist_id: None,
+            reserved_for: None,
+            bundle_id: None,
+            pending_buyer_id: None,
+        }
+    }
+}
+
+//Las eduforms V1 no tenían idioma, se asumen en inglés
+//V1 eduforms had no language, they are assumed to be in English
+impl From<EduFormV1> for EduForm {
+    fn from(form: EduFormV1) -> Self {
+        EduForm {
+            locale: "en".to_string(),
+            title: form.title,
+            supply: form.supply,
+            website: form.website,
+            twitter: form.twitter,
+            discord: form.discord,
+            instagram: form.instagram,
+            descriptions: form.descriptions,
+            nft_contract_ids: vec![],
+            category: None,
+            tags: vec![],
+            images: form.images,
+            translations: HashMap::new(),
+        }
+    }
+}
+
+//Reescribe cada venta y eduform con el layout actual, el gas crece con su número
+//Rewrite every sale and eduform with the current layout, the gas grows with their number
+impl From<ContractV1> for Contract {
+    fn from(mut old: ContractV1) -> Self {
+        let mut contract = Contract::new(old.owner.clone(), None);
+
+        let mut sale_owners = vec![];
+        for (contract_and_token_id, sale) in old.sales.to_vec() {
+            let sale: Sale = sale.into();
+            old.sales.insert_raw(&contract_and_token_id.try_to_vec().unwrap(), &sale.try_to_vec().unwrap());
+            sale_owners.push((contract_and_token_id, sale.owner_id, sale.nft_contract_id));
+        }
+        let mut forms = vec![];
+        for (form_id, form) in old.edu_forms.to_vec() {
+            let form: EduForm = form.into();
+            old.edu_forms.insert_raw(&form_id.try_to_vec().unwrap(), &form.try_to_vec().unwrap());
+            forms.push((form_id, form));
+        }
+
+        //Las colecciones guardan solo su prefijo y largo, se conservan con el tipo nuevo
+        //The collections only store their prefix and length, they are kept with the new type
+        contract.sales = retype_collection(&old.sales);
+        contract.edu_forms = retype_collection(&old.edu_forms);
+        contract.by_owner_id = old.by_owner_id;
+        contract.internal_rebuild_sales_by_owner(sale_owners);
+        contract.by_nft_contract_id = old.by_nft_contract_id;
+        contract.storage_deposits = old.storage_deposits;
+        contract.cart = old.cart;
+        contract.edu_form_number = old.edu_form_number;
+
+        for (form_id, form) in forms {
+            contract.internal_index_form(form_id, &form);
+        }
+
+        //Los admins anteriores pasan a tener el rol Admin
+        //The previous admins get the Admin role
+        let mut admins = UnorderedSet::new(StorageKey::RoleMembersInner { role: Role::Admin }.try_to_vec().unwrap());
+        for admin in old.admins.iter() {
+            admins.insert(&admin);
+        }
+        if !admins.is_empty() {
+            contract.role_members.insert(&Role::Admin, &admins);
+        }
+        old.admins.clear();
+
+        contract
+    }
+}
+
+impl Contract {
+    //V1 guardaba los sets de by_owner_id bajo el contrato NFT (con el prefijo del owner), se rehacen desde las ventas
+    //V1 stored the by_owner_id sets under the NFT contract (with the owner prefix), they are rebuilt from the sales
+    fn internal_rebuild_sales_by_owner(&mut self, sale_owners: Vec<(ContractAndTokenId, AccountId, String)>) {
+        for (_, _, nft_contract_id) in &sale_owners {
+            if let Some(mut sales) = nft_contract_id.parse().ok().and_then(|id: AccountId| self.by_owner_id.remove(&id)) {
+                sales.clear();
+            }
+        }
+
+        let mut by_owner_id: HashMap<AccountId, UnorderedSet<ContractAndTokenId>> = HashMap::new();
+        for (contract_and_token_id, owner_id, _) in sale_owners {
+            let prefix = StorageKey::ByOwnerIdInner { account_id_hash: hash_account_id(&owner_id) }.try_to_vec().unwrap();
+            //Cada set de V1 empezaba vacío sobre el mismo prefijo, quedan índices huérfanos que harían que insert ignore la venta
+            //Each V1 set started empty over the same prefix, orphan indexes are left that would make insert skip the sale
+            let mut stale_index: LookupMap<ContractAndTokenId, u64> = LookupMap::new([prefix.as_slice(), b"i"].concat());
+            stale_index.remove(&contract_and_token_id);
+            by_owner_id
+                .entry(owner_id)
+                .or_insert_with(|| UnorderedSet::new(prefix))
+                .insert(&contract_and_token_id);
+        }
+        for (owner_id, sales) in by_owner_id {
+            self.by_owner_id.insert(&owner_id, &sales);
+        }
+    }
+}
+
+fn retype_collection<A: BorshSerialize, B: BorshDeserialize>(collection: &A) -> B {
+    B::try_from_slice(&collection.try_to_vec().unwrap()).unwrap()
+}
+
+#[near_bindgen]
+impl Contract {
+    //Despliega el wasm recibido como input (sin JSON) y llama migrate en el mismo batch
+    //Deploy the wasm received as input (without JSON) and call migrate in the same batch
+    //El sha256 del wasm debe estar aprobado con una propuesta UpgradeContract y solo se usa una vez
+    //The sha256 of the wasm must be approved by an UpgradeContract proposal and it is only used once
+    pub fn upgrade(&mut self) -> Promise {
+        require!(self.has_role(Role::Upgrader, env::predecessor_account_id()), "Only upgraders can upgrade the contract");
+        let code = env::input().expect("The wasm code must be the input of upgrade");
+        let approved_hash = self.approved_upgrade_hash.take().expect("There is no approved upgrade");
+        require!(env::sha256(&code) == approved_hash, "The wasm doesn't match the approved upgrade");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), vec![], 0, GAS_FOR_MIGRATE)
+    }
+
+    //Convierte el estado guardado al layout actual
+    //Convert the stored state to the current layout
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = match VersionedContract::read() {
+            VersionedContract::V1(old) => (*old).into(),
+            VersionedContract::V2(contract) => *contract,
+        };
+        env::storage_write(STATE_VERSION_KEY, &[STATE_VERSION]);
+        contract
+    }
+
+    pub fn get_approved_upgrade_hash(&self) -> Option<Base58CryptoHash> {
+        self.approved_upgrade_hash.map(Base58CryptoHash::from)
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        env::storage_read(STATE_VERSION_KEY).map(|version| version[0]).unwrap_or(1)
+    }
+}
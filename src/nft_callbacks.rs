@@ -9,11 +9,35 @@ use crate::*;
 #[serde(crate = "near_sdk::serde")]
 pub struct SaleArgs {
     pub sale_conditions: SalePriceInYoctoNear,
+    //Allowlist opcional, solo sus cuentas pueden comprar
+    //Optional allowlist, only its accounts can buy
+    #[serde(default)]
+    pub allowlist_id: Option<AllowlistId>,
+    //Venta privada, solo esta cuenta puede comprar
+    //Private sale, only this account can buy
+    #[serde(default)]
+    pub reserved_for: Option<AccountId>,
+}
+
+//msg de nft_approve para aprobar un token a un trade en vez de listarlo
+//nft_approve msg to approve a token to a trade instead of listing it
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TradeArgs {
+    pub trade_id: TradeId,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum ApproveArgs {
+    Trade(TradeArgs),
+    Rental(RentalArgs),
+    Sale(SaleArgs),
 }
 
 //Trait para el callback del Contrato NFT
 //Trait for the NFT contract callback
-trait NonFungibleTokenApprovalsReceiver {
+pub(crate) trait NonFungibleTokenApprovalsReceiver {
     fn nft_on_approve(
         &mut self,
         token_id: TokenId,
@@ -52,13 +76,29 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
             signer_id,
             "nft_on_approve should only be called via cross-contract call"
         );
-        //Nos aseguramos de que el signer sea el owner del nft
-        //We make sure that the signer is the owner of the nft
-        assert_eq!(
-            owner_id,
-            signer_id,
-            "nft_on_approve should only be called by the owner of the nft"
-        );
+        //El owner_id lo reporta el contrato NFT, si el owner es una DAO o un multisig el signer es otra cuenta
+        //The owner_id is reported by the NFT contract, if the owner is a DAO or a multisig the signer is another account
+        //En ese caso solo confiamos en colecciones aprobadas en el registro
+        //In that case we only trust collections approved in the registry
+        if owner_id != signer_id {
+            self.internal_assert_collection_trusted(&nft_contract_id);
+        }
+
+        //No se lista nada mientras Listing esté pausado
+        //Nothing is listed while Listing is paused
+        self.internal_assert_not_paused(PauseScope::Listing);
+
+        //Verificamos que la colección pueda listar en el marketplace
+        //Check that the collection can list on the marketplace
+        self.internal_assert_collection_can_list(&nft_contract_id);
+
+        //Si el msg es para un trade o una renta no se crea ninguna venta
+        //If the msg is for a trade or a rental no sale is created
+        let sale_args = if let Some(sale_args) = self.internal_sale_args_from_msg(&msg, &nft_contract_id, &token_id, &owner_id, approval_id) {
+            sale_args
+        } else {
+            return;
+        };
 
         //Verificar si hay storage suficiente
         //Check if there is enough storage
@@ -68,30 +108,58 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
         let storage_amount: u128 = self.storage_minimum_balance().0;
         //Obtenemos el storage pagado por el owner
         //Get the storage paid by the owner
-        let owner_paid_storage: u128 = self.storage_deposits.get(&signer_id).unwrap_or(0);
+        let owner_paid_storage: u128 = self.storage_deposits.get(&owner_id).unwrap_or(0);
         //Obtener el storage requerido (storage por (numero de ventas mas 1))
         //Get the storage required (storage by (number of sales plus 1))
-        let signer_storage_required = (self.get_supply_by_owner_id(signer_id).0+1) as u128 * storage_amount;
+        let owner_storage_required = (self.get_supply_by_owner_id(owner_id.clone()).0 + self.internal_rental_count(&owner_id) + 1) as u128 * storage_amount
+            //Más el storage bloqueado por sus eduforms
+            //Plus the storage locked by their eduforms
+            + self.form_storage_locked.get(&owner_id).unwrap_or(0);
 
         //Comprobamos que el storage pagado por el owner sea >= suficiente
         //Check that the owner paid storage is >= sufficient
         assert!(
-            owner_paid_storage >= signer_storage_required,
+            owner_paid_storage >= owner_storage_required,
             "The owner paid storage is not sufficient: {}, for {} sales at {} rate of per sale",
-            owner_paid_storage, signer_storage_required / STORAGE_PER_SALE, STORAGE_PER_SALE
+            owner_paid_storage, owner_storage_required / STORAGE_PER_SALE, STORAGE_PER_SALE
         );
 
         //Si todo fue correcto, añadimos la venta
         //If everything was correct, add the sale
-        let SaleArgs { sale_conditions } =
-            //Las condiciones vienen del msg, el market asume que el usuario ha pasado msg correcto, si no panic
-            //The conditions come from the msg, the market assumes that the user has passed a correct msg, if not panic
-            near_sdk::serde_json::from_str(&msg).expect("Failed to deserialize msg, not valid");
+        let SaleArgs { sale_conditions, allowlist_id, reserved_for } = sale_args;
+        if let Some(allowlist_id) = allowlist_id {
+            assert!(self.allowlists.get(&allowlist_id).is_some(), "Allowlist not found");
+        }
+        if let Some(reserved_for) = &reserved_for {
+            assert_ne!(reserved_for, &owner_id, "A sale can't be reserved for its owner");
+        }
         
         //Creamos el ID unico de la venta (contract + DELIMETER + token_id)
         //Create the unique ID of the sale (contract + DELIMETER + token_id)
         let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
 
+        //Si el mismo owner vuelve a aprobar el token sigue en su bundle, con una compra en curso no se puede volver a listar
+        //If the same owner approves again the token stays in its bundle, with a purchase in progress it can't be listed again
+        let existing = self.sales.get(&contract_and_token_id);
+        if let Some(sale) = &existing {
+            assert!(sale.pending_buyer_id.is_none(), "This sale has a purchase in progress");
+        }
+        let bundle_id = match existing {
+            Some(sale) if sale.owner_id == owner_id => sale.bundle_id,
+            //Con un owner nuevo la venta anterior sale de los indices del owner anterior y su bundle se deshace
+            //With a new owner the previous sale leaves the indexes of the previous owner and its bundle is undone
+            Some(sale) => {
+                self.internal_try_remove_sale(&contract_and_token_id);
+                if let Some(bundle_id) = sale.bundle_id {
+                    if self.bundles.get(&bundle_id).is_some() {
+                        self.internal_remove_bundle(bundle_id, true);
+                    }
+                }
+                None
+            }
+            None => None,
+        };
+
         //Insertamos el valor en el mapa de ventas, la llave es el ID unico, value es el objeto Sale
         //Insert the value in the map of sales, the key is the unique ID, value is the object Sale
         self.sales.insert(
@@ -102,6 +170,10 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
                 nft_contract_id: nft_contract_id.to_string(), //NFT Contract ID = ID del contrato NFT
                 token_id: token_id.clone(),//Token ID = ID del token
                 sale_conditions, //Sale Conditions = Condiciones de la venta
+                allowlist_id, //Allowlist = Allowlist de compradores
+                reserved_for, //Reserved for = Comprador reservado
+                bundle_id, //Bundle ID = ID del bundle
+                pending_buyer_id: None, //Pending buyer = Comprador con una compra en curso
             },
         );
 
@@ -126,9 +198,9 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
         //Insert the token ID in the set
         //by_owner_id.insert(&token_id);
         by_owner_id.insert(&contract_and_token_id);
-        //insertamos el set de vuelta a la coleccion por el contract ID de NFT
-        //insert the set back to the collection by the NFT contract ID
-        self.by_owner_id.insert(&nft_contract_id, &by_owner_id);
+        //insertamos el set de vuelta a la coleccion por el owner ID
+        //insert the set back to the collection by the owner ID
+        self.by_owner_id.insert(&owner_id, &by_owner_id);
 
         //Obtener el token ID del contrato nft dado, si no hay creamos un set vacio
         //get the token IDs for the given nft contract ID. If there are none, we create a new empty set
@@ -155,4 +227,31 @@ impl NonFungibleTokenApprovalsReceiver for Contract {
         self.by_nft_contract_id.insert(&nft_contract_id, &by_nft_contract_id);
     }
     
-}
\ No newline at end of file
+}
+
+impl Contract {
+    //Las condiciones vienen del msg, el market asume que el usuario ha pasado msg correcto, si no panic
+    //The conditions come from the msg, the market assumes that the user has passed a correct msg, if not panic
+    //Los msg de trades y rentas se procesan aquí y no retornan SaleArgs
+    //Trade and rental msgs are processed here and don't return SaleArgs
+    pub fn internal_sale_args_from_msg(
+        &mut self,
+        msg: &str,
+        nft_contract_id: &AccountId,
+        token_id: &TokenId,
+        owner_id: &AccountId,
+        approval_id: u64,
+    ) -> Option<SaleArgs> {
+        match near_sdk::serde_json::from_str(msg).expect("Failed to deserialize msg, not valid") {
+            ApproveArgs::Trade(TradeArgs { trade_id }) => {
+                self.internal_on_trade_approve(trade_id, nft_contract_id.clone(), token_id.clone(), owner_id.clone(), approval_id);
+                None
+            }
+            ApproveArgs::Rental(rental_args) => {
+                self.internal_on_rental_approve(rental_args, nft_contract_id.clone(), token_id.clone(), owner_id.clone(), approval_id);
+                None
+            }
+            ApproveArgs::Sale(sale_args) => Some(sale_args),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Partes del marketplace que se pueden pausar por separado durante un incidente
+//Parts of the marketplace that can be paused separately during an incident
+//Los retiros (storage_withdraw, remove_sale, remove_rental, cancel_trade...) nunca se pausan
+//Withdrawals (storage_withdraw, remove_sale, remove_rental, cancel_trade...) are never paused
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PauseScope {
+    //nft_on_approve, create_bundle y propose_trade
+    //nft_on_approve, create_bundle and propose_trade
+    Listing,
+    //offer, offer_bundle, accept_trade, rent, buy_from_drop y buy_from_other_marketplaces
+    //offer, offer_bundle, accept_trade, rent, buy_from_drop and buy_from_other_marketplaces
+    Purchases,
+    //Crear y editar eduforms, sus quizzes y drops
+    //Create and edit eduforms, their quizzes and drops
+    FormEdits,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn pause(&mut self, scope: PauseScope) {
+        let account_id = env::predecessor_account_id();
+        require!(self.has_role(Role::Pauser, account_id.clone()), "Only pausers can pause the marketplace");
+        require!(!self.paused_scopes.contains(&scope), "The scope is already paused");
+        self.paused_scopes.push(scope);
+        log_event("evie", "1.0.0", "paused", near_sdk::serde_json::json!({
+            "scope": scope,
+            "account_id": account_id,
+        }));
+    }
+
+    pub fn unpause(&mut self, scope: PauseScope) {
+        let account_id = env::predecessor_account_id();
+        require!(self.has_role(Role::Pauser, account_id.clone()), "Only pausers can unpause the marketplace");
+        require!(self.paused_scopes.contains(&scope), "The scope is not paused");
+        self.paused_scopes.retain(|paused| paused != &scope);
+        log_event("evie", "1.0.0", "unpaused", near_sdk::serde_json::json!({
+            "scope": scope,
+            "account_id": account_id,
+        }));
+    }
+
+    pub fn is_paused(&self, scope: PauseScope) -> bool {
+        self.paused_scopes.contains(&scope)
+    }
+
+    pub fn get_paused_scopes(&self) -> Vec<PauseScope> {
+        self.paused_scopes.clone()
+    }
+}
+
+impl Contract {
+    pub fn internal_assert_not_paused(&self, scope: PauseScope) {
+        require!(!self.paused_scopes.contains(&scope), format!("{:?} is paused", scope));
+        //Mientras RemoveAllForms borra por lotes no se crean ni editan eduforms
+        //While RemoveAllForms deletes in batches no eduforms are created or edited
+        if scope == PauseScope::FormEdits {
+            require!(!self.remove_all_forms_in_progress, "All forms are being removed");
+        }
+    }
+}
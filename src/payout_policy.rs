@@ -0,0 +1,146 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Límites que debe cumplir el payout de un contrato NFT, en puntos básicos del precio
+//Limits the payout of an NFT contract must meet, in basis points of the price
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PayoutPolicy {
+    //Máximo para todas las cuentas que no son el vendedor
+    //Maximum for every account that is not the seller
+    pub max_royalty_bps: u32,
+    //Mínimo que debe recibir el vendedor
+    //Minimum the seller must receive
+    pub min_seller_bps: u32,
+}
+
+//Resultado de preview_payout, reason explica por qué el payout no es válido
+//Result of preview_payout, reason explains why the payout is not valid
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PayoutPreview {
+    pub payout: Option<HashMap<AccountId, U128>>,
+    pub valid: bool,
+    pub reason: Option<String>,
+}
+
+#[near_bindgen]
+impl Contract {
+    //La política cambia con una propuesta SetPayoutPolicy, esto abre la propuesta y devuelve su id
+    //The policy changes through a SetPayoutPolicy proposal, this opens the proposal and returns its id
+    #[payable]
+    pub fn set_payout_policy(&mut self, policy: PayoutPolicy) -> U64 {
+        U64(self.propose(ProposalAction::SetPayoutPolicy { policy }))
+    }
+
+    pub fn get_payout_policy(&self) -> PayoutPolicy {
+        self.payout_policy.clone()
+    }
+
+    //Consulta nft_payout del contrato NFT al precio de la venta y lo valida como lo haría resolve_purchase
+    //Query nft_payout of the NFT contract at the sale price and validate it as resolve_purchase would
+    pub fn preview_payout(&self, nft_contract_id: AccountId, token_id: TokenId) -> Promise {
+        let sale = self
+            .sales
+            .get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id))
+            .expect("No sale found");
+        ext_contract::ext(nft_contract_id)
+            .with_static_gas(GAS_FOR_NFT_PAYOUT)
+            .nft_payout(token_id, sale.sale_conditions, MAX_ROYALTIES_ACCOUNTS.into())
+            .then(ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_PREVIEW_PAYOUT)
+                .resolve_preview_payout(sale.owner_id, sale.sale_conditions))
+    }
+
+    #[private]
+    pub fn resolve_preview_payout(&self, seller_id: AccountId, price: U128) -> PayoutPreview {
+        let payout = match promise_result_as_success() {
+            Some(value) => match self.internal_valid_payout(&value, price.0) {
+                Some(payout) => payout,
+                None => return PayoutPreview { payout: None, valid: false, reason: Some("Invalid payout".to_string()) },
+            },
+            None => return PayoutPreview { payout: None, valid: false, reason: Some("nft_payout failed".to_string()) },
+        };
+        let reason = self.internal_check_payout_policy(&payout, price.0, &seller_id).err();
+        PayoutPreview { valid: reason.is_none(), payout: Some(payout), reason }
+    }
+}
+
+impl Contract {
+    pub fn internal_assert_valid_payout_policy(&self, policy: &PayoutPolicy) {
+        require!(
+            policy.max_royalty_bps <= 10_000 && policy.min_seller_bps <= 10_000,
+            "The payout policy is in basis points, at most 10000"
+        );
+    }
+
+    pub fn internal_check_payout_policy(
+        &self,
+        payout: &HashMap<AccountId, U128>,
+        price: u128,
+        seller_id: &AccountId,
+    ) -> Result<(), String> {
+        let seller_amount = payout.get(seller_id).map(|amount| amount.0).unwrap_or(0);
+        let royalties: u128 = payout.iter()
+            .filter(|(account_id, _)| *account_id != seller_id)
+            .map(|(_, amount)| amount.0)
+            .sum();
+        let min_seller = price / 10_000 * u128::from(self.payout_policy.min_seller_bps);
+        let max_royalties = price / 10_000 * u128::from(self.payout_policy.max_royalty_bps);
+        if seller_amount < min_seller {
+            return Err(format!("The seller receives {}, less than the minimum {}", seller_amount, min_seller));
+        }
+        if royalties > max_royalties {
+            return Err(format!("The royalties are {}, more than the maximum {}", royalties, max_royalties));
+        }
+        Ok(())
+    }
+
+    //Payout válido para compras, bundles y preview_payout: maximo MAX_ROYALTIES_ACCOUNTS cuentas y resto de 0 o 1
+    //Valid payout for purchases, bundles and preview_payout: at most MAX_ROYALTIES_ACCOUNTS accounts and a remainder of 0 or 1
+    pub fn internal_valid_payout(&self, value: &[u8], price: u128) -> Option<HashMap<AccountId, U128>> {
+        let payout_object = near_sdk::serde_json::from_slice::<Payout>(value).ok()?;
+        if payout_object.payout.len() > MAX_ROYALTIES_ACCOUNTS as usize || payout_object.payout.is_empty() {
+            env::log_str(&format!("The payout has more than {} accounts", MAX_ROYALTIES_ACCOUNTS));
+            return None;
+        }
+        let mut remainder = price;
+        for value in payout_object.payout.values() {
+            remainder = remainder.checked_sub(value.0)?;
+        }
+        if remainder == 0 || remainder == 1 {
+            Some(payout_object.payout)
+        } else {
+            env::log_str("Payout error, remainder is not 0 or 1");
+            None
+        }
+    }
+
+    //Si el payout rompe la política se registra un evento y se trata como un payout inválido
+    //If the payout breaks the policy an event is logged and it is treated as an invalid payout
+    pub fn internal_payout_within_policy(
+        &self,
+        payout: HashMap<AccountId, U128>,
+        price: u128,
+        seller_id: &AccountId,
+        buyer_id: &AccountId,
+        nft_contract_id: &AccountId,
+        token_id: &TokenId,
+    ) -> Option<HashMap<AccountId, U128>> {
+        match self.internal_check_payout_policy(&payout, price, seller_id) {
+            Ok(()) => Some(payout),
+            Err(reason) => {
+                log_event("evie", "1.0.0", "payout_rejected", near_sdk::serde_json::json!({
+                    "nft_contract_id": nft_contract_id,
+                    "token_id": token_id,
+                    "seller_id": seller_id,
+                    "buyer_id": buyer_id,
+                    "price": U128(price),
+                    "reason": reason,
+                }));
+                None
+            }
+        }
+    }
+}
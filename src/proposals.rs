@@ -0,0 +1,252 @@
+use near_sdk::require;
+
+use crate::*;
+
+//Acciones de alto impacto que solo se ejecutan con una propuesta aprobada
+//High-impact actions that only run through an approved proposal
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ProposalAction {
+    RemoveAllForms,
+    //Propone el nuevo owner, que todavía debe llamar accept_ownership
+    //Proposes the new owner, who still has to call accept_ownership
+    TransferOwnership { new_owner: AccountId, expires_in: Option<U64> },
+    //Retira fondos del pool de storage de eduforms
+    //Withdraws funds from the eduform storage pool
+    WithdrawStoragePool { receiver_id: AccountId, amount: U128 },
+    SetProposalConfig { config: ProposalConfig },
+    //El rol Admin solo se otorga o quita con propuestas
+    //The Admin role is only granted or revoked through proposals
+    GrantAdmin { account_id: AccountId },
+    RevokeAdmin { account_id: AccountId },
+    //Aprueba el sha256 del wasm que un Upgrader puede desplegar con upgrade
+    //Approves the sha256 of the wasm an Upgrader can deploy with upgrade
+    UpgradeContract { code_hash: Base58CryptoHash },
+    //Es la comisión del contrato, solo cambia con el multisig
+    //It is the fee setting of the contract, it only changes through the multisig
+    SetPayoutPolicy { policy: PayoutPolicy },
+}
+
+//threshold aprobaciones de admins (al menos MIN_PROPOSAL_THRESHOLD) y timelock en nanosegundos desde que se alcanza
+//threshold admin approvals (at least MIN_PROPOSAL_THRESHOLD) and timelock in nanoseconds since it is reached
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalConfig {
+    pub threshold: u32,
+    pub timelock: U64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proposal {
+    pub proposer: AccountId,
+    pub action: ProposalAction,
+    pub approvals: Vec<AccountId>,
+    pub created_at: U64,
+    //Momento en que llegó al threshold, desde ahí corre el timelock
+    //Moment it reached the threshold, the timelock runs from there
+    pub approved_at: Option<U64>,
+    //Storage que pagó el proponente, cada aprobación extra la paga quien aprueba
+    //Storage paid by the proposer, each extra approval is paid by the approver
+    pub storage_deposit: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalWithId {
+    pub id: ProposalId,
+    pub proposal: Proposal,
+}
+
+#[near_bindgen]
+impl Contract {
+    //Un admin propone una acción y cuenta como su primera aprobación, el storage lo paga su deposito adjunto
+    //An admin proposes an action and it counts as its first approval, the attached deposit pays the storage
+    #[payable]
+    pub fn propose(&mut self, action: ProposalAction) -> ProposalId {
+        let proposer = env::predecessor_account_id();
+        require!(self.has_role(Role::Admin, proposer.clone()), "Only admins can create proposals");
+        match &action {
+            ProposalAction::SetProposalConfig { config } => require!(
+                config.threshold >= MIN_PROPOSAL_THRESHOLD,
+                format!("The threshold must be at least {}", MIN_PROPOSAL_THRESHOLD)
+            ),
+            ProposalAction::GrantAdmin { account_id } => require!(
+                !self.internal_is_role_member(Role::Admin, account_id),
+                "The account already has this role"
+            ),
+            ProposalAction::RevokeAdmin { account_id } => require!(
+                self.internal_is_role_member(Role::Admin, account_id),
+                "The account doesn't have this role"
+            ),
+            ProposalAction::SetPayoutPolicy { policy } => self.internal_assert_valid_payout_policy(policy),
+            _ => {}
+        }
+        let initial_storage_usage = env::storage_usage();
+        let proposal_id = self.next_proposal_id;
+        let mut proposal = Proposal {
+            proposer: proposer.clone(),
+            action,
+            approvals: vec![proposer],
+            created_at: U64(env::block_timestamp()),
+            approved_at: None,
+            storage_deposit: U128(0),
+        };
+        self.internal_update_proposal_approval(&mut proposal);
+        log_event("evie", "1.0.0", "proposal_created", near_sdk::serde_json::json!({
+            "proposal_id": U64(proposal_id),
+            "proposer": proposal.proposer,
+            "action": proposal.action,
+        }));
+        self.proposals.insert(&proposal_id, &proposal);
+        self.next_proposal_id += 1;
+
+        //Incluye los bytes de approved_at, que se llenan cuando llega al threshold
+        //Includes the approved_at bytes, filled in when the threshold is reached
+        let bytes = env::storage_usage() - initial_storage_usage + APPROVED_AT_STORAGE;
+        let cost = Balance::from(bytes) * STORAGE_PRICE_PER_BYTE;
+        let deposit = env::attached_deposit();
+        require!(deposit >= cost, format!("Attach at least {} yoctoNEAR for the proposal storage", cost));
+        proposal.storage_deposit = U128(cost);
+        self.proposals.insert(&proposal_id, &proposal);
+        if deposit > cost {
+            Promise::new(proposal.proposer).transfer(deposit - cost);
+        }
+        proposal_id
+    }
+
+    //Quien aprueba paga el storage de su aprobación, se le devuelve al ejecutar o cancelar
+    //The approver pays the storage of its approval, it is returned on execution or cancellation
+    #[payable]
+    pub fn approve_proposal(&mut self, proposal_id: ProposalId) {
+        let account_id = env::predecessor_account_id();
+        require!(self.has_role(Role::Admin, account_id.clone()), "Only admins can approve proposals");
+        let mut proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        require!(!proposal.approvals.contains(&account_id), "The proposal is already approved by this account");
+        let cost = approval_storage_cost(&account_id);
+        let deposit = env::attached_deposit();
+        require!(deposit >= cost, format!("Attach at least {} yoctoNEAR for the approval storage", cost));
+        proposal.approvals.push(account_id.clone());
+        self.internal_update_proposal_approval(&mut proposal);
+        log_event("evie", "1.0.0", "proposal_approved", near_sdk::serde_json::json!({
+            "proposal_id": U64(proposal_id),
+            "account_id": account_id,
+            "approved_at": proposal.approved_at,
+        }));
+        self.proposals.insert(&proposal_id, &proposal);
+        if deposit > cost {
+            Promise::new(account_id).transfer(deposit - cost);
+        }
+    }
+
+    //Cualquier admin la ejecuta cuando tiene threshold aprobaciones vigentes y pasó el timelock
+    //Any admin executes it once it has threshold current approvals and the timelock has passed
+    pub fn execute_proposal(&mut self, proposal_id: ProposalId) {
+        require!(self.has_role(Role::Admin, env::predecessor_account_id()), "Only admins can execute proposals");
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        require!(
+            self.internal_proposal_approvals(&proposal) >= self.proposal_config.threshold,
+            format!("The proposal needs {} admin approvals", self.proposal_config.threshold)
+        );
+        let approved_at = proposal.approved_at.expect("The proposal is not approved");
+        let unlock_at = approved_at.0 + self.proposal_config.timelock.0;
+        require!(env::block_timestamp() >= unlock_at, format!("The proposal is locked until {}", unlock_at));
+
+        self.proposals.remove(&proposal_id);
+        self.internal_refund_proposal_storage(&proposal);
+        log_event("evie", "1.0.0", "proposal_executed", near_sdk::serde_json::json!({
+            "proposal_id": U64(proposal_id),
+            "action": proposal.action,
+        }));
+
+        match proposal.action {
+            ProposalAction::RemoveAllForms => self.internal_remove_all_forms(REMOVE_ALL_FORMS_BATCH),
+            ProposalAction::TransferOwnership { new_owner, expires_in } => {
+                self.internal_propose_owner(new_owner, expires_in)
+            }
+            ProposalAction::WithdrawStoragePool { receiver_id, amount } => {
+                require!(self.form_storage_pool >= amount.0, "The form storage pool doesn't have enough funds");
+                self.form_storage_pool -= amount.0;
+                Promise::new(receiver_id).transfer(amount.0);
+            }
+            ProposalAction::SetProposalConfig { config } => self.proposal_config = config,
+            ProposalAction::GrantAdmin { account_id } => self.internal_grant_role(Role::Admin, account_id),
+            ProposalAction::RevokeAdmin { account_id } => self.internal_revoke_role(Role::Admin, account_id),
+            ProposalAction::UpgradeContract { code_hash } => self.approved_upgrade_hash = Some(code_hash.into()),
+            ProposalAction::SetPayoutPolicy { policy } => {
+                log_event("evie", "1.0.0", "payout_policy_updated", &policy);
+                self.payout_policy = policy;
+            }
+        }
+    }
+
+    //El proponente o el owner pueden cancelar una propuesta pendiente
+    //The proposer or the owner can cancel a pending proposal
+    pub fn cancel_proposal(&mut self, proposal_id: ProposalId) {
+        let account_id = env::predecessor_account_id();
+        let proposal = self.proposals.get(&proposal_id).expect("Proposal not found");
+        require!(
+            proposal.proposer == account_id || self.owner == account_id,
+            "Only the proposer or the owner can cancel a proposal"
+        );
+        self.proposals.remove(&proposal_id);
+        self.internal_refund_proposal_storage(&proposal);
+        log_event("evie", "1.0.0", "proposal_cancelled", near_sdk::serde_json::json!({
+            "proposal_id": U64(proposal_id),
+            "account_id": account_id,
+        }));
+    }
+
+    pub fn get_proposal(&self, proposal_id: ProposalId) -> Option<Proposal> {
+        self.proposals.get(&proposal_id)
+    }
+
+    //Propuestas pendientes (las ejecutadas y canceladas se borran)
+    //Pending proposals (executed and cancelled ones are deleted)
+    pub fn get_proposals(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<ProposalWithId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.proposals.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(id, proposal)| ProposalWithId { id, proposal })
+            .collect()
+    }
+
+    pub fn get_proposal_config(&self) -> ProposalConfig {
+        self.proposal_config.clone()
+    }
+}
+
+impl Contract {
+    //Solo cuentan las aprobaciones de cuentas que siguen siendo admins
+    //Only approvals of accounts that are still admins count
+    pub fn internal_proposal_approvals(&self, proposal: &Proposal) -> u32 {
+        proposal.approvals.iter()
+            .filter(|account_id| self.has_role(Role::Admin, (*account_id).clone()))
+            .count() as u32
+    }
+
+    //Devuelve el storage al proponente y a cada cuenta que aprobó después
+    //Return the storage to the proposer and to each account that approved afterwards
+    pub fn internal_refund_proposal_storage(&self, proposal: &Proposal) {
+        if proposal.storage_deposit.0 > 0 {
+            Promise::new(proposal.proposer.clone()).transfer(proposal.storage_deposit.0);
+        }
+        for account_id in proposal.approvals.iter().filter(|account_id| **account_id != proposal.proposer) {
+            Promise::new(account_id.clone()).transfer(approval_storage_cost(account_id));
+        }
+    }
+
+    pub fn internal_update_proposal_approval(&self, proposal: &mut Proposal) {
+        if proposal.approved_at.is_none() && self.internal_proposal_approvals(proposal) >= self.proposal_config.threshold {
+            proposal.approved_at = Some(U64(env::block_timestamp()));
+        }
+    }
+}
+
+//Una aprobación guarda el largo (u32) y los bytes del account id
+//An approval stores the length (u32) and the bytes of the account id
+pub(crate) fn approval_storage_cost(account_id: &AccountId) -> Balance {
+    Balance::from(4 + account_id.as_str().len() as u64) * STORAGE_PRICE_PER_BYTE
+}
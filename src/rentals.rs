@@ -0,0 +1,427 @@
+use near_sdk::require;
+
+use crate::*;
+
+//msg de nft_approve para poner un NFT en renta: {"price_per_day": "...", "max_days": 30}
+//nft_approve msg to list an NFT for rent: {"price_per_day": "...", "max_days": 30}
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RentalArgs {
+    pub price_per_day: U128,
+    pub max_days: u32,
+}
+
+//El NFT queda con su owner, el marketplace lo retiene por su approval y registra al arrendatario
+//The NFT stays with its owner, the marketplace holds it through its approval and records the renter
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Rental {
+    pub owner_id: AccountId,
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    pub approval_id: u64,
+    pub price_per_day: U128,
+    pub max_days: u32,
+    pub renter_id: Option<AccountId>,
+    pub lease_end: Option<U64>,
+    //Inicio de la renta y precio por día con el que se rentó
+    //Start of the lease and price per day it was rented at
+    pub lease_start: Option<U64>,
+    pub lease_price_per_day: U128,
+    //Renta que retiene el marketplace y la ya pagada al owner, se paga por día empezado
+    //Rent held by the marketplace and the one already paid to the owner, it is paid per started day
+    pub rent_held: U128,
+    pub rent_paid: U128,
+}
+
+impl Rental {
+    pub fn is_leased(&self) -> bool {
+        self.lease_end.map(|lease_end| env::block_timestamp() < lease_end.0).unwrap_or(false)
+    }
+
+    //Renta ganada por el owner hasta ahora, cada día cuenta desde que empieza
+    //Rent earned by the owner so far, each day counts from its start
+    pub fn rent_earned(&self) -> u128 {
+        match (self.lease_start, self.lease_end) {
+            (Some(lease_start), Some(lease_end)) => {
+                let days = (lease_end.0 - lease_start.0) / RENTAL_DAY;
+                let started = (env::block_timestamp().saturating_sub(lease_start.0) / RENTAL_DAY + 1).min(days);
+                self.lease_price_per_day.0 * u128::from(started)
+            }
+            _ => 0,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    //Renta el NFT por days dias, se verifica con nft_token que el owner y el approval sigan vigentes
+    //Rent the NFT for days days, nft_token verifies that the owner and the approval are still valid
+    #[payable]
+    pub fn rent(&mut self, nft_contract_id: AccountId, token_id: TokenId, days: u32) -> Promise {
+        self.internal_assert_not_paused(PauseScope::Purchases);
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut rental = self.rentals.get(&contract_and_token_id).expect("No rental found");
+        let renter_id = env::predecessor_account_id();
+        require!(renter_id != rental.owner_id, "You can't rent your own NFT");
+        require!(!rental.is_leased(), "The NFT is already rented");
+        require!(days > 0 && days <= rental.max_days, format!("days must be between 1 and {}", rental.max_days));
+
+        let cost = rental.price_per_day.0 * u128::from(days);
+        let deposit = env::attached_deposit();
+        require!(deposit >= cost, format!("Deposit must be at least the rent: {}", cost));
+
+        //Pagamos al owner lo que quede de la renta anterior
+        //Pay the owner what is left of the previous lease
+        self.internal_end_lease(&mut rental);
+        //Reservamos la renta, se revierte en resolve_rent si el NFT ya no está disponible
+        //Reserve the rent, it is reverted in resolve_rent if the NFT is not available anymore
+        rental.renter_id = Some(renter_id.clone());
+        rental.lease_start = Some(U64(env::block_timestamp()));
+        rental.lease_end = Some(U64(env::block_timestamp() + u64::from(days) * RENTAL_DAY));
+        rental.lease_price_per_day = rental.price_per_day;
+        self.rentals.insert(&contract_and_token_id, &rental);
+
+        ext_paras::ext(nft_contract_id.clone())
+            .with_static_gas(self.internal_gas_config(&nft_contract_id).cross_contract_call())
+            .nft_token(token_id.clone())
+            .then(ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_RENT)
+                .resolve_rent(nft_contract_id, token_id, renter_id, U128(deposit), U128(cost)))
+    }
+
+    //Si el token sigue siendo del owner y aprobado al marketplace se retiene la renta, si no se devuelve todo
+    //If the token still belongs to the owner and is approved to the marketplace the rent is held, otherwise everything is refunded
+    //La renta se paga al owner por día empezado con claim_rent, return_rental o check_rental
+    //The rent is paid to the owner per started day with claim_rent, return_rental or check_rental
+    #[private]
+    pub fn resolve_rent(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        renter_id: AccountId,
+        deposit: U128,
+        cost: U128,
+    ) -> bool {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let rental = self.rentals.get(&contract_and_token_id);
+        let token = promise_result_as_success()
+            .and_then(|value| near_sdk::serde_json::from_slice::<Option<Token>>(&value).ok().flatten());
+
+        let valid = match (&rental, &token) {
+            (Some(rental), Some(token)) => {
+                rental.renter_id.as_ref() == Some(&renter_id)
+                    && token.owner_id == rental.owner_id
+                    && token.approved_account_ids.get(&env::current_account_id()) == Some(&rental.approval_id)
+            }
+            _ => false,
+        };
+
+        if valid {
+            let mut rental = rental.unwrap();
+            rental.rent_held = cost;
+            self.rentals.insert(&contract_and_token_id, &rental);
+            if deposit.0 > cost.0 {
+                Promise::new(renter_id.clone()).transfer(deposit.0 - cost.0);
+            }
+            env::log_str(&format!("{} rented {} {}", renter_id, nft_contract_id, token_id));
+            return true;
+        }
+
+        //El listado ya no es valido, devolvemos el deposito y quitamos la renta
+        //The listing is not valid anymore, refund the deposit and remove the rental
+        Promise::new(renter_id.clone()).transfer(deposit.0);
+        if rental.map(|rental| rental.renter_id == Some(renter_id)).unwrap_or(false) {
+            self.internal_remove_rental(&contract_and_token_id);
+        }
+        env::log_str(&format!("Rent of {} {} failed, the NFT is not available", nft_contract_id, token_id));
+        false
+    }
+
+    //Cualquiera puede terminar una renta vencida, el owner recibe el resto de la renta y el NFT queda disponible otra vez
+    //Anyone can end an expired rental, the owner gets the rest of the rent and the NFT is available again
+    pub fn return_rental(&mut self, nft_contract_id: AccountId, token_id: TokenId) {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut rental = self.rentals.get(&contract_and_token_id).expect("No rental found");
+        require!(rental.renter_id.is_some(), "The NFT is not rented");
+        require!(!rental.is_leased(), "The lease has not expired");
+        let renter_id = rental.renter_id.clone().unwrap();
+        self.internal_end_lease(&mut rental);
+        self.rentals.insert(&contract_and_token_id, &rental);
+        env::log_str(&format!("{} returned {} {}", renter_id, nft_contract_id, token_id));
+    }
+
+    //Cualquiera puede pagar al owner la renta de los días ya empezados
+    //Anyone can pay the owner the rent of the days already started
+    pub fn claim_rent(&mut self, nft_contract_id: AccountId, token_id: TokenId) -> U128 {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut rental = self.rentals.get(&contract_and_token_id).expect("No rental found");
+        let paid = self.internal_pay_rent(&mut rental);
+        self.rentals.insert(&contract_and_token_id, &rental);
+        U128(paid)
+    }
+
+    //Cualquiera puede verificar con nft_token una renta en curso, si el owner o el approval cambiaron
+    //la renta termina: el owner cobra los días empezados y el arrendatario recupera el resto
+    //Anyone can verify an ongoing lease with nft_token, if the owner or the approval changed
+    //the lease ends: the owner gets the started days and the renter gets the rest back
+    pub fn check_rental(&mut self, nft_contract_id: AccountId, token_id: TokenId) -> Promise {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let rental = self.rentals.get(&contract_and_token_id).expect("No rental found");
+        require!(rental.is_leased(), "The NFT is not rented");
+        ext_paras::ext(nft_contract_id.clone())
+            .with_static_gas(self.internal_gas_config(&nft_contract_id).cross_contract_call())
+            .nft_token(token_id.clone())
+            .then(ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_RENT)
+                .resolve_check_rental(nft_contract_id, token_id, rental.renter_id.unwrap()))
+    }
+
+    //Retorna true si la renta sigue vigente, si nft_token falló no se cambia nada
+    //Return true if the lease is still valid, if nft_token failed nothing changes
+    #[private]
+    pub fn resolve_check_rental(&mut self, nft_contract_id: AccountId, token_id: TokenId, renter_id: AccountId) -> bool {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut rental = match self.rentals.get(&contract_and_token_id) {
+            Some(rental) if rental.renter_id.as_ref() == Some(&renter_id) => rental,
+            _ => return false,
+        };
+        let token = match promise_result_as_success()
+            .and_then(|value| near_sdk::serde_json::from_slice::<Option<Token>>(&value).ok())
+        {
+            Some(token) => token,
+            None => return true,
+        };
+        let valid = matches!(
+            token,
+            Some(token) if token.owner_id == rental.owner_id
+                && token.approved_account_ids.get(&env::current_account_id()) == Some(&rental.approval_id)
+        );
+        if !valid {
+            self.internal_end_lease(&mut rental);
+            self.internal_remove_rental(&contract_and_token_id);
+            env::log_str(&format!("Rental of {} {} ended, the owner or the approval changed", nft_contract_id, token_id));
+        }
+        valid
+    }
+
+    //El owner quita su NFT de renta cuando no está rentado
+    //The owner removes their NFT from rent when it is not rented
+    #[payable]
+    pub fn remove_rental(&mut self, nft_contract_id: AccountId, token_id: TokenId) {
+        assert_one_yocto();
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let rental = self.rentals.get(&contract_and_token_id).expect("No rental found");
+        assert_eq!(env::predecessor_account_id(), rental.owner_id, "Only the owner can remove a rental");
+        require!(!rental.is_leased(), "The NFT is rented");
+        self.internal_remove_rental(&contract_and_token_id);
+    }
+
+    //Cuenta que puede usar el NFT: el arrendatario durante la renta, si no el owner
+    //Account that can use the NFT: the renter during the lease, otherwise the owner
+    pub fn get_current_user(&self, nft_contract_id: AccountId, token_id: TokenId) -> Option<AccountId> {
+        self.rentals
+            .get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id))
+            .map(|rental| if rental.is_leased() { rental.renter_id.unwrap() } else { rental.owner_id })
+    }
+
+    pub fn get_rental(&self, nft_contract_id: AccountId, token_id: TokenId) -> Option<Rental> {
+        self.rentals.get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id))
+    }
+
+    pub fn get_rentals(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Rental> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.rentals.values()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    //nft_on_approve con RentalArgs, el storage se cobra como una venta más
+    //nft_on_approve with RentalArgs, the storage is charged as one more sale
+    pub fn internal_on_rental_approve(
+        &mut self,
+        args: RentalArgs,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+    ) {
+        require!(args.max_days > 0, "max_days must be greater than zero");
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        //Si el token cambió de owner la renta anterior ya no vale, si vencía se cierra la renta
+        //If the token changed owner the previous rental is not valid anymore, if it expired the lease is closed
+        let existing = match self.rentals.get(&contract_and_token_id) {
+            Some(mut rental) if rental.owner_id != owner_id => {
+                self.internal_end_lease(&mut rental);
+                self.internal_remove_rental(&contract_and_token_id);
+                None
+            }
+            Some(mut rental) if !rental.is_leased() => {
+                self.internal_end_lease(&mut rental);
+                Some(rental)
+            }
+            existing => existing,
+        };
+
+        if existing.is_none() {
+            let required = (u128::from(self.get_supply_by_owner_id(owner_id.clone()).0) + u128::from(self.internal_rental_count(&owner_id)) + 1)
+                * STORAGE_PER_SALE
+                + self.form_storage_locked.get(&owner_id).unwrap_or(0);
+            let paid = self.storage_deposits.get(&owner_id).unwrap_or(0);
+            require!(
+                paid >= required,
+                format!("The owner paid storage is not sufficient: {}, {} required", paid, required)
+            );
+        }
+
+        //Si se vuelve a aprobar durante una renta, la renta actual se mantiene
+        //If it is approved again during a lease, the current lease is kept
+        let rental = match existing {
+            Some(rental) => Rental { approval_id, price_per_day: args.price_per_day, max_days: args.max_days, ..rental },
+            None => Rental {
+                owner_id: owner_id.clone(),
+                nft_contract_id,
+                token_id,
+                approval_id,
+                price_per_day: args.price_per_day,
+                max_days: args.max_days,
+                renter_id: None,
+                lease_end: None,
+                lease_start: None,
+                lease_price_per_day: U128(0),
+                rent_held: U128(0),
+                rent_paid: U128(0),
+            },
+        };
+        self.rentals.insert(&contract_and_token_id, &rental);
+
+        let mut rentals = self.rentals_by_owner_id.get(&owner_id).unwrap_or_else(|| {
+            UnorderedSet::new(
+                StorageKey::RentalsByOwnerIdInner {
+                    account_id_hash: hash_account_id(&owner_id),
+                }
+                .try_to_vec()
+                .unwrap(),
+            )
+        });
+        rentals.insert(&contract_and_token_id);
+        self.rentals_by_owner_id.insert(&owner_id, &rentals);
+    }
+
+    pub fn internal_remove_rental(&mut self, contract_and_token_id: &ContractAndTokenId) {
+        let rental = self.rentals.remove(contract_and_token_id).expect("No rental found");
+        if let Some(mut rentals) = self.rentals_by_owner_id.get(&rental.owner_id) {
+            rentals.remove(contract_and_token_id);
+            if rentals.is_empty() {
+                self.rentals_by_owner_id.remove(&rental.owner_id);
+            } else {
+                self.rentals_by_owner_id.insert(&rental.owner_id, &rentals);
+            }
+        }
+    }
+
+    //Paga al owner la renta ganada que todavía retiene el marketplace
+    //Pay the owner the earned rent the marketplace still holds
+    pub fn internal_pay_rent(&mut self, rental: &mut Rental) -> u128 {
+        let owed = rental.rent_earned().saturating_sub(rental.rent_paid.0).min(rental.rent_held.0);
+        if owed > 0 {
+            rental.rent_held = U128(rental.rent_held.0 - owed);
+            rental.rent_paid = U128(rental.rent_paid.0 + owed);
+            Promise::new(rental.owner_id.clone()).transfer(owed);
+        }
+        owed
+    }
+
+    //Cierra la renta: el owner cobra lo ganado y el arrendatario recupera lo que quede retenido
+    //Close the lease: the owner gets what was earned and the renter gets back what is still held
+    pub fn internal_end_lease(&mut self, rental: &mut Rental) {
+        self.internal_pay_rent(rental);
+        if let Some(renter_id) = rental.renter_id.take() {
+            if rental.rent_held.0 > 0 {
+                Promise::new(renter_id).transfer(rental.rent_held.0);
+            }
+        }
+        rental.lease_end = None;
+        rental.lease_start = None;
+        rental.lease_price_per_day = U128(0);
+        rental.rent_held = U128(0);
+        rental.rent_paid = U128(0);
+    }
+
+    pub fn internal_rental_count(&self, owner_id: &AccountId) -> u64 {
+        self.rentals_by_owner_id.get(owner_id).map(|rentals| rentals.len()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    const RENTAL_MSG: &str = r#"{"price_per_day": "1000000000000000000000000", "max_days": 10}"#;
+
+    //bob renta por 3 días a 1 NEAR el token 1 de alice en el momento 0
+    //bob rents the token 1 of alice for 3 days at 1 NEAR at time 0
+    fn setup_lease() -> Contract {
+        let mut contract = new_contract();
+        let alice = account("alice.near");
+        let bob = account("bob.near");
+        deposit_storage(&mut contract, &alice, 1);
+        approve(&mut contract, &alice, "1", 1, RENTAL_MSG);
+
+        set_context(context(&bob).attached_deposit(3 * ONE_NEAR), vec![]);
+        contract.rent(nft(), "1".to_string(), 3);
+        set_callback_context(0, vec![token_owned_by(&alice, Some(1))]);
+        assert!(contract.resolve_rent(nft(), "1".to_string(), bob, U128(3 * ONE_NEAR), U128(3 * ONE_NEAR)));
+        contract
+    }
+
+    #[test]
+    fn broken_lease_refunds_the_renter() {
+        let mut contract = setup_lease();
+        let now = RENTAL_DAY + RENTAL_DAY / 2;
+        set_context(context(&account("dave.near")).block_timestamp(now), vec![]);
+        contract.check_rental(nft(), "1".to_string());
+
+        //alice vendió el token a carol en el segundo día: alice cobra 2 días y bob recupera 1
+        //alice sold the token to carol on the second day: alice gets 2 days and bob gets 1 back
+        set_callback_context(now, vec![token_owned_by(&account("carol.near"), None)]);
+        assert!(!contract.resolve_check_rental(nft(), "1".to_string(), account("bob.near")));
+
+        assert_eq!(transferred_to(&account("alice.near")), 2 * ONE_NEAR);
+        assert_eq!(transferred_to(&account("bob.near")), ONE_NEAR);
+        assert!(contract.get_rental(nft(), "1".to_string()).is_none());
+        assert_eq!(contract.internal_rental_count(&account("alice.near")), 0);
+    }
+
+    #[test]
+    fn valid_lease_is_kept() {
+        let mut contract = setup_lease();
+        set_callback_context(RENTAL_DAY, vec![token_owned_by(&account("alice.near"), Some(1))]);
+        assert!(contract.resolve_check_rental(nft(), "1".to_string(), account("bob.near")));
+
+        assert_eq!(transferred_to(&account("bob.near")), 0);
+        assert_eq!(contract.get_current_user(nft(), "1".to_string()), Some(account("bob.near")));
+    }
+
+    #[test]
+    fn approval_by_a_new_owner_ends_the_lease() {
+        //carol aprueba el token en el primer día: alice cobra 1 día y bob recupera 2
+        //carol approves the token on the first day: alice gets 1 day and bob gets 2 back
+        let mut contract = setup_lease();
+        let carol = account("carol.near");
+        deposit_storage(&mut contract, &carol, 1);
+        approve(&mut contract, &carol, "1", 2, RENTAL_MSG);
+
+        assert_eq!(transferred_to(&account("alice.near")), ONE_NEAR);
+        assert_eq!(transferred_to(&account("bob.near")), 2 * ONE_NEAR);
+        let rental = contract.get_rental(nft(), "1".to_string()).unwrap();
+        assert_eq!(rental.owner_id, carol);
+        assert_eq!(rental.renter_id, None);
+        assert_eq!(contract.internal_rental_count(&account("alice.near")), 0);
+    }
+}
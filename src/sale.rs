@@ -1,5 +1,5 @@
 use crate::*;
-use near_sdk::promise_result_as_success;
+use near_sdk::{promise_result_as_success, PromiseOrValue};
 
 //Estructura que almacena infromación importante acerca de cada sale en el market
 //Structure that stores important information about each sale in the market
@@ -21,6 +21,29 @@ pub struct Sale {
     //precio en yoctoNEAR en el que está listado el token
     //sale price in yoctoNEAR that the token is listed for
     pub sale_conditions: SalePriceInYoctoNear,
+    //allowlist que deben cumplir los compradores
+    //allowlist the buyers must be in
+    pub allowlist_id: Option<AllowlistId>,
+    //cuenta para la que está reservada la venta (venta privada)
+    //account the sale is reserved for (private sale)
+    pub reserved_for: Option<AccountId>,
+    //bundle al que pertenece la venta, solo se puede comprar con el bundle
+    //bundle the sale belongs to, it can only be bought with the bundle
+    pub bundle_id: Option<BundleId>,
+    //comprador con una compra en curso, la venta queda bloqueada hasta resolve_purchase
+    //buyer with a purchase in progress, the sale is locked until resolve_purchase
+    pub pending_buyer_id: Option<AccountId>,
+}
+
+//Venta junto con la eduform de su proyecto, sin la eduform el JSON es igual al de Sale
+//Sale together with the eduform of its project, without the eduform the JSON is the same as Sale
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SaleWithForm {
+    #[serde(flatten)]
+    pub sale: Sale,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub form: Option<EduFormWithId>,
 }
 
 #[near_bindgen]
@@ -32,6 +55,12 @@ impl Contract {
         //Por seguridad verificamos que se haya anezado un solo yocto
         //For security assert one yocto
         assert_one_yocto();
+        //Una venta con una compra en curso no se puede quitar hasta que se resuelva
+        //A sale with a purchase in progress can't be removed until it is resolved
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        if let Some(sale) = self.sales.get(&contract_and_token_id) {
+            assert!(sale.pending_buyer_id.is_none(), "This sale has a purchase in progress");
+        }
         //Obtenemos el objeto sale como valor de retorno removiendo el sale ID internamente
         //get the sale object as the return value from removing the sale internally
         let sale = self.internal_remove_sale(nft_contract_id.into(), token_id);
@@ -41,6 +70,7 @@ impl Contract {
         //Si la verificación falla se revierte el remove
         //If the verification fails the remove is reversed
         assert_eq!(owner_id, sale.owner_id, "Only the owner can remove a sale");
+        assert!(sale.bundle_id.is_none(), "Remove the bundle before removing its sales");
     }
 
     //Actualiza el precio de una venta del market
@@ -75,10 +105,35 @@ impl Contract {
         self.sales.insert(&contract_and_token_id, &sale);
     }
 
+    //Abre una venta privada sin volver a listar, la allowlist de la venta se mantiene
+    //Open a private sale without relisting, the allowlist of the sale is kept
+    #[payable]
+    pub fn open_sale(&mut self, nft_contract_id: AccountId, token_id: String) {
+        //Por seguridad verificamos que se haya anezado un solo yocto
+        //For security assert one yocto
+        assert_one_yocto();
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut sale = self
+            .sales
+            .get(&contract_and_token_id)
+            .expect("No sale found");
+        assert_eq!(
+            env::predecessor_account_id(), sale.owner_id,
+            "Only the owner can open a sale"
+        );
+        sale.reserved_for = None;
+        self.sales.insert(&contract_and_token_id, &sale);
+    }
+
     //Poner oferta en una venta espacifica, la venta se llevará a cabo siempre que su depósito sea >= al precio de lista
     //Put an offer on a specific sale, the sale will be carried out always that the deposit >= the listed price
+    //Se paga exactamente el precio de lista y el exceso se devuelve, max_price protege si el owner subió el precio
+    //Exactly the list price is paid and the surplus is refunded, max_price protects if the owner raised the price
+    //proof solo se necesita si la venta usa una allowlist Merkle
+    //proof is only needed if the sale uses a Merkle allowlist
     #[payable]
-    pub fn offer(&mut self, nft_contract_id: AccountId, token_id: String) {
+    pub fn offer(&mut self, nft_contract_id: AccountId, token_id: String, proof: Option<Vec<Base64VecU8>>, max_price: Option<U128>) {
+        self.internal_assert_not_paused(PauseScope::Purchases);
         //Obtener el deposito adjunto y verificar que sea mayor que cero
         //Get the attached deposit and assert that it is greater than zero
         let deposit = env::attached_deposit();
@@ -103,6 +158,18 @@ impl Contract {
             buyer_id, sale.owner_id,
             "I catch you, you can't offer on your own sale."
         );
+        assert!(sale.bundle_id.is_none(), "This sale is part of a bundle, use offer_bundle");
+        assert!(sale.pending_buyer_id.is_none(), "This sale has a purchase in progress");
+        //Si la venta es privada, solo la cuenta reservada puede comprar
+        //If the sale is private, only the reserved account can buy
+        if let Some(reserved_for) = &sale.reserved_for {
+            assert_eq!(&buyer_id, reserved_for, "This sale is reserved for another account");
+        }
+        //Si la venta tiene allowlist, el comprador debe estar en ella
+        //If the sale has an allowlist, the buyer must be in it
+        if let Some(allowlist_id) = sale.allowlist_id {
+            self.internal_assert_allowlisted(allowlist_id, &buyer_id, proof);
+        }
         //Obtenemos el precio de la venta en u128 (punto 0 convierte de U128 a u128)
         //get the sale price in u128 (dot 0 converts from U128 to u128)
         let price = sale.sale_conditions.0;
@@ -113,10 +180,24 @@ impl Contract {
             "Deposit must be greater than or equal to the current price: {:?}",
             price
         );
+        //Si el owner subió el precio mientras la transacción estaba en camino, no compramos
+        //If the owner raised the price while the transaction was in flight, we don't buy
+        if let Some(max_price) = max_price {
+            assert!(
+                price <= max_price.0,
+                "The current price {} is higher than max_price {}",
+                price, max_price.0
+            );
+        }
+        //Devolvemos el exceso, la compra se liquida al precio de lista
+        //Refund the surplus, the purchase settles at the list price
+        if deposit > price {
+            Promise::new(buyer_id.clone()).transfer(deposit - price);
+        }
 
-        //Procesamos la compra (Esta función remueve la venta, transfiere dinero y distribuye royalties)
-        //Process the purchase (This function removes the sale, transfers money and distributes royalties)
-        self.process_purchase(contract_id, token_id, U128(deposit), buyer_id);
+        //Procesamos la compra (Esta función bloquea la venta, transfiere dinero y distribuye royalties)
+        //Process the purchase (This function locks the sale, transfers money and distributes royalties)
+        self.process_purchase(contract_id, token_id, U128(price), buyer_id);
     }
 
     //Función privada que se encarga de procesar la compra
@@ -129,22 +210,69 @@ impl Contract {
         price: U128,
         buyer_id: AccountId,
     ) -> Promise {
-        //Obtiene el objeto sale removiendo la venta
-        //Get the sale object removing the sale
-        let sale = self.internal_remove_sale(nft_contract_id.clone(), token_id.clone());
+        //Bloqueamos la venta hasta que se resuelva la transferencia, resolve_purchase la quita o la restaura
+        //Lock the sale until the transfer is resolved, resolve_purchase removes or restores it
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let mut sale = self.sales.get(&contract_and_token_id).expect("No sale found");
+        sale.pending_buyer_id = Some(buyer_id.clone());
+        self.sales.insert(&contract_and_token_id, &sale);
+        let gas_config = self.internal_gas_config(&nft_contract_id);
+        //Antes de transferir consultamos el payout al precio de la venta para validarlo con la política
+        //Before transferring we query the payout at the sale price to validate it with the policy
+        ext_contract::ext(nft_contract_id.clone())
+            .with_static_gas(GAS_FOR_NFT_PAYOUT)
+            .nft_payout(token_id.clone(), price, MAX_ROYALTIES_ACCOUNTS.into())
+            .then(ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_PURCHASE_PAYOUT + gas_config.nft_transfer() + gas_config.royalties())
+                .resolve_purchase_payout(nft_contract_id, token_id, sale.owner_id, buyer_id, price))
+    }
+
+    //Si el payout consultado es válido y cumple la política transferimos el token, si no devolvemos el pago y desbloqueamos la venta
+    //If the queried payout is valid and meets the policy we transfer the token, otherwise we refund the payment and unlock the sale
+    #[private]
+    pub fn resolve_purchase_payout(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        seller_id: AccountId,
+        buyer_id: AccountId,
+        price: U128,
+    ) -> PromiseOrValue<U128> {
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        let sale = self.sales.get(&contract_and_token_id)
+            .filter(|sale| sale.pending_buyer_id.as_ref() == Some(&buyer_id));
+        let payout = promise_result_as_success()
+            .and_then(|value| self.internal_valid_payout(&value, price.0))
+            .and_then(|payout| self.internal_payout_within_policy(payout, price.0, &seller_id, &buyer_id, &nft_contract_id, &token_id));
+        let sale = match (sale, payout) {
+            (Some(sale), Some(_)) => sale,
+            (sale, _) => {
+                //Si el payout es None devolvemos el dinero al comprador y la venta sigue listada
+                //If the payout is None we return the money to the buyer and the sale stays listed
+                if let Some(mut sale) = sale {
+                    sale.pending_buyer_id = None;
+                    self.sales.insert(&contract_and_token_id, &sale);
+                }
+                Promise::new(buyer_id).transfer(price.0);
+                return PromiseOrValue::Value(price);
+            }
+        };
+        //Gas del contrato NFT, puede tener overrides en gas_configs
+        //Gas of the NFT contract, it can have overrides in gas_configs
+        let gas_config = self.internal_gas_config(&nft_contract_id);
         //Iniciamos una llamada a otro contrato (El contrato del nft), esto transferirá tokens
         //al comprador y regresará un payout al market para distribuir los fondos a las cuentas apropiadas
         //Start a call to another contract (the nft contract), this will transfer tokens
         //to the buyer and return a payout to the market to distribute the funds to the appropriate accounts
         ext_contract::ext(
-            nft_contract_id, //NFT Contract ID for start the cross contract call = ID del contrato del nft para iniciar la llamada
+            nft_contract_id.clone(), //NFT Contract ID for start the cross contract call = ID del contrato del nft para iniciar la llamada
         ).with_attached_deposit(
             1 //yoctoNEAR attached = YoctoNEAR adjunto
         ).with_static_gas(
-            GAS_FOR_NFT_TRANSFER //Gas for NFT transfer = Gas para transferir el nft
+            gas_config.nft_transfer() //Gas for NFT transfer = Gas para transferir el nft
         ).nft_transfer_payout(
             buyer_id.clone(),                      //Purchaser = Comprador
-            token_id,                              //Token ID = ID del token
+            token_id.clone(),                      //Token ID = ID del token
             sale.approval_id,                      //Market Approval ID = ID del market aprobado
             "payout from Evie Market".to_string(), //Memo
             price,                                 //Price = Precio de la venta
@@ -156,81 +284,74 @@ impl Contract {
         .then(ext_self::ext(
             env::current_account_id(), //Invoked in this contract = Invocado en este contrato
         ).with_static_gas(
-            GAS_FOR_ROYALTIES
+            gas_config.royalties()
         ).resolve_purchase(
+            nft_contract_id, //Para quitar o restaurar la venta
+            token_id, //In order to remove or restore the sale
+            seller_id, //Para validar el payout con la política = To validate the payout with the policy
             buyer_id, //Este parametro es necesario, en caso de error, para devolver al comprador
             price, //The parameters are necesaries in case of error, in order to refund to the buyer
         )
     
-    )
+    ).into()
     }
 
     //Función privada que resuelve la promesa, verifica que no haya habido problema, si todo está correcto paga a las cuentas,
     // y si no, devuelve el dinero al comprador
     //Private function that resolves the promise, verifies that there is no problem, if everything is correct, pays the accounts
     // and if not, returns the money to the buyer
+    //Si el token se transfirió nunca se devuelve el pago: con un payout distinto al consultado todo va al vendedor
+    //If the token was transferred the payment is never refunded: with a payout different from the queried one everything goes to the seller
     #[private]
-    pub fn resolve_purchase(&mut self, buyer_id: AccountId, price: U128) -> U128 {
+    pub fn resolve_purchase(
+        &mut self,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        seller_id: AccountId,
+        buyer_id: AccountId,
+        price: U128,
+    ) -> U128 {
+        let result = promise_result_as_success();
+        //Si el token se transfirió la venta se quita, si no se desbloquea y sigue listada
+        //If the token was transferred the sale is removed, otherwise it is unlocked and stays listed
+        //Solo si sigue bloqueada por este comprador, y sin panics para no perder el reembolso
+        //Only if it is still locked by this buyer, and without panics so the refund is not lost
+        let contract_and_token_id = format!("{}{}{}", nft_contract_id, DELIMETER, token_id);
+        if let Some(mut sale) = self.sales.get(&contract_and_token_id) {
+            if sale.pending_buyer_id.as_ref() == Some(&buyer_id) {
+                if result.is_some() {
+                    self.internal_try_remove_sale(&contract_and_token_id);
+                } else {
+                    sale.pending_buyer_id = None;
+                    self.sales.insert(&contract_and_token_id, &sale);
+                }
+            }
+        }
+
+        //Si la transferencia falló devolvemos el dinero al comprador
+        //If the transfer failed we return the money to the buyer
+        let value = if let Some(value) = result {
+            value
+        } else {
+            Promise::new(buyer_id).transfer(u128::from(price));
+            return price;
+        };
+
         //Verifica la información del payout retornada del metodo nft_transfer_payout
         //Verify the information returned from the nft_transfer_payout method
-        let payout_option = promise_result_as_success().and_then(|value| {
-            //Si el payout es None significa que algo salió mal y devolvemos el dinero al comprador
-            //If the payout is None it means that something went wrong and we return the money to the buyer
-            near_sdk::serde_json::from_slice::<Payout>(&value)
-                //Convertimos el valor a opcional
-                //Convert the value to an optional
-                .ok()
-                //Retornamos None si el valor es None
-                //Return None if the value is None
-                .and_then(|payout_object| {
-                    //Revisamos si length de payout es > 10 (Máximo de cuentas para el payout) o si es vacio, en ese caso retornamos None
-                    //Check if the length of payout is > 10 (Maximum accounts for payout) or if it is empty, in that case return None
-                    if payout_object.payout.len() > MAX_ROYALTIES_ACCOUNTS as usize
-                        || payout_object.payout.is_empty()
-                    {
-                        let err_msg_max_roy_amount = format!(
-                            "The payout has more than {} accounts",
-                            MAX_ROYALTIES_ACCOUNTS
-                        );
-                        env::log_str(&err_msg_max_roy_amount);
-                        None
-                    } else {
-                        //Si es de un largo correcto
-                        //If it is a correct length
-                        //Mantendremos el monto de cuanto pagar
-                        //We keep the amount to pay
-                        let mut remainder = price.0;
-                        //Iteramos sobre los ids de las cuentas y restemos los pagos
-                        //Iterate over the IDs of the accounts and subtract the payments
-                        for &value in payout_object.payout.values() {
-                            //Buscamos errores de overflow
-                            //Find overflow errors
-                            remainder = remainder.checked_sub(value.0)?;
-                        }
-                        //Si el resto es 0 o 1, significa que todo está correcto
-                        //If the remainder is 0 or 1, it means that everything is correct
-                        if remainder == 0 || remainder == 1 {
-                            //Retornamos el objecto payout porque no hay ningún error
-                            //Return the payout object because there are no errors
-                            Some(payout_object.payout)
-                        } else {
-                            //Si no, significa que hay un error, retornamos None
-                            //If it is not, it means that there is an error, we return None
-                            env::log_str("Payout error, remainder is not 0 or 1");
-                            None
-                        }
-                    }
-                })
-        });
+        let payout_option = self.internal_valid_payout(&value, price.0)
+        //Verificamos que el payout cumpla la política de royalties y la parte minima del vendedor
+        //Check that the payout meets the royalty policy and the minimum seller share
+        .and_then(|payout| self.internal_payout_within_policy(payout, price.0, &seller_id, &buyer_id, &nft_contract_id, &token_id));
         //Si devolvimos un valor en payout, seteamos la variable
         //If we return a value in payout, we set the variable
         let payout = if let Some(payout_option) = payout_option {
             payout_option
         } else {
-            //Si el payout es None devolvemos el dinero al comprador
-            //If the payout is None we return the money to the buyer
-            Promise::new(buyer_id).transfer(u128::from(price));
-            return price;
+            //El token ya es del comprador, el precio completo va al vendedor
+            //The token already belongs to the buyer, the whole price goes to the seller
+            env::log_str(&format!("Invalid payout for {}, paying {} to the seller", token_id, price.0));
+            HashMap::from([(seller_id, price)])
         };
 
         //Payouts
@@ -245,7 +366,15 @@ impl Contract {
 //Here is the function that is executed when the cross contract is invoked
 #[ext_contract(ext_self)]
 trait ExtSelf {
-    fn resolve_purchase(&mut self, buyer_id: AccountId, price: U128) -> Promise;
+    fn resolve_purchase_payout(&mut self, nft_contract_id: AccountId, token_id: TokenId, seller_id: AccountId, buyer_id: AccountId, price: U128) -> PromiseOrValue<U128>;
+    fn resolve_purchase(&mut self, nft_contract_id: AccountId, token_id: TokenId, seller_id: AccountId, buyer_id: AccountId, price: U128) -> U128;
+    fn resolve_bundle_purchase(&mut self, bundle_id: BundleId, buyer_id: AccountId, legs: Vec<BundleLeg>) -> U128;
+    fn resolve_preview_payout(&self, seller_id: AccountId, price: U128) -> PayoutPreview;
+    fn resolve_trade_escrow(&mut self, trade_id: TradeId, check_owner: bool) -> bool;
+    fn resolve_escrow_deliveries(&mut self, transfers: Vec<EscrowTransfer>) -> bool;
+    fn resolve_rent(&mut self, nft_contract_id: AccountId, token_id: TokenId, renter_id: AccountId, deposit: U128, cost: U128) -> bool;
+    fn resolve_check_rental(&mut self, nft_contract_id: AccountId, token_id: TokenId, renter_id: AccountId) -> bool;
+    fn resolve_drop_mint(&mut self, form_id: FormId, buyer_id: AccountId, price: U128, treasury_id: AccountId, proceeds: U128) -> bool;
 }
 
 // #[ext_contract(ext_nft)]
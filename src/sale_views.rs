@@ -100,12 +100,15 @@ impl Contract {
 
     //Retornamos numero de ventas por contrato nft (retorna Vector de sales)
     //Returns the number of sales by contract nft (Return Vector of sales)
+    //Si with_form es true, cada venta incluye la eduform del proyecto
+    //If with_form is true, each sale includes the eduform of the project
     pub fn get_sales_by_nft_contract_id(
         &self,
         nft_contract_id: AccountId,
         from_index: Option<U128>,
         limit: Option<u64>,
-    ) -> Vec<Sale> {
+        with_form: Option<bool>,
+    ) -> Vec<SaleWithForm> {
         //Obtenemos el set de token IDs a la venta del contrato nft dado
         //Get the set of token IDs for sale of the given nft contract ID
         let by_nft_contract_id = self.by_nft_contract_id.get(&nft_contract_id);
@@ -126,6 +129,14 @@ impl Contract {
         //Convert the UnorderedSet to a Vector of Strings
         let keys = sales.as_vector();
 
+        //Obtenemos la eduform del proyecto solo si se pidió
+        //Get the eduform of the project only if it was requested
+        let form = if with_form.unwrap_or(false) {
+            self.internal_form_by_nft_contract_id(&nft_contract_id)
+        } else {
+            None
+        };
+
         //iteramos sobre las llaves del vector
         //iterate over the keys of the vector
         keys.iter()
@@ -137,7 +148,10 @@ impl Contract {
         .take(limit.unwrap_or(0) as usize)
         //Mapeamos los token IDs en objectos Sale pasando el ID unico (contract + DELIMETER + token ID)
         //Map the token IDs to Sale objects passing the unique ID (contract + DELIMETER + token ID)
-        .map(|token_id| self.sales.get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id)).unwrap())
+        .map(|token_id| SaleWithForm {
+            sale: self.sales.get(&format!("{}{}{}", nft_contract_id, DELIMETER, token_id)).unwrap(),
+            form: form.clone(),
+        })
         //Regresamos a un Vector
         //Return to a Vector
         .collect()
@@ -0,0 +1,101 @@
+//Utilidades para los tests unitarios: contexto del marketplace y lectura de los receipts creados
+//Utilities for the unit tests: marketplace context and reading of the created receipts
+use near_sdk::mock::VmAction;
+use near_sdk::test_utils::{get_created_receipts, VMContextBuilder};
+use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+
+use crate::nft_callbacks::NonFungibleTokenApprovalsReceiver;
+use crate::*;
+
+pub(crate) const NFT: &str = "nft.near";
+
+pub(crate) fn account(name: &str) -> AccountId {
+    name.parse().unwrap()
+}
+
+pub(crate) fn marketplace() -> AccountId {
+    account("market.near")
+}
+
+pub(crate) fn nft() -> AccountId {
+    account(NFT)
+}
+
+//Llamada de predecessor al marketplace, el signer es el mismo predecessor
+//Call from predecessor to the marketplace, the signer is the predecessor itself
+pub(crate) fn context(predecessor: &AccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(marketplace())
+        .signer_account_id(predecessor.clone())
+        .predecessor_account_id(predecessor.clone());
+    builder
+}
+
+pub(crate) fn set_context(builder: &VMContextBuilder, promise_results: Vec<PromiseResult>) {
+    testing_env!(builder.build(), VMConfig::test(), RuntimeFeesConfig::test(), Default::default(), promise_results);
+}
+
+//Callback del marketplace con los resultados de las promesas anteriores
+//Marketplace callback with the results of the previous promises
+pub(crate) fn set_callback_context(timestamp: u64, promise_results: Vec<PromiseResult>) {
+    set_context(context(&marketplace()).block_timestamp(timestamp), promise_results);
+}
+
+pub(crate) fn new_contract() -> Contract {
+    set_context(&context(&account("owner.near")), vec![]);
+    Contract::new(account("owner.near"), None)
+}
+
+pub(crate) fn deposit_storage(contract: &mut Contract, account_id: &AccountId, sales: u128) {
+    set_context(context(account_id).attached_deposit(STORAGE_PER_SALE * sales), vec![]);
+    contract.storage_deposit(None);
+}
+
+//nft_approve del owner en el contrato NFT, que llama nft_on_approve del marketplace
+//nft_approve of the owner on the NFT contract, which calls nft_on_approve of the marketplace
+pub(crate) fn approve(contract: &mut Contract, owner_id: &AccountId, token_id: &str, approval_id: u64, msg: &str) {
+    set_context(context(&nft()).signer_account_id(owner_id.clone()), vec![]);
+    contract.nft_on_approve(token_id.to_string(), owner_id.clone(), approval_id, msg.to_string());
+}
+
+pub(crate) fn success<T: Serialize>(value: &T) -> PromiseResult {
+    PromiseResult::Successful(near_sdk::serde_json::to_vec(value).unwrap())
+}
+
+pub(crate) fn token_owned_by(owner_id: &AccountId, approval_id: Option<u64>) -> PromiseResult {
+    success(&Token {
+        owner_id: owner_id.clone(),
+        approved_account_ids: approval_id.map(|id| HashMap::from([(marketplace(), id)])).unwrap_or_default(),
+        next_approval_id: 0,
+        royalty: HashMap::new(),
+    })
+}
+
+//Suma de las transferencias de NEAR a la cuenta en los receipts creados
+//Sum of the NEAR transfers to the account in the created receipts
+pub(crate) fn transferred_to(account_id: &AccountId) -> Balance {
+    get_created_receipts().iter()
+        .filter(|receipt| &receipt.receiver_id == account_id)
+        .flat_map(|receipt| receipt.actions.iter())
+        .map(|action| match action {
+            VmAction::Transfer { deposit } => *deposit,
+            _ => 0,
+        })
+        .sum()
+}
+
+//(token_id, receiver_id) de cada nft_transfer creado
+//(token_id, receiver_id) of each created nft_transfer
+pub(crate) fn nft_transfers() -> Vec<(String, String)> {
+    get_created_receipts().iter()
+        .flat_map(|receipt| receipt.actions.iter())
+        .filter_map(|action| match action {
+            VmAction::FunctionCall { function_name, args, .. } if function_name == "nft_transfer" => {
+                let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+                Some((args["token_id"].as_str().unwrap().to_string(), args["receiver_id"].as_str().unwrap().to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,405 @@
+use near_sdk::{require, PromiseResult};
+
+use crate::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TradeToken {
+    pub nft_contract_id: AccountId,
+    pub token_id: TokenId,
+    //Se llena cuando el owner aprueba el marketplace con msg {"trade_id": ...}
+    //Filled when the owner approves the marketplace with msg {"trade_id": ...}
+    #[serde(default)]
+    pub approval_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TradeStatus {
+    Open,
+    //Los tokens se están moviendo, ya no se puede cancelar
+    //The tokens are being moved, it can't be cancelled anymore
+    Settling,
+}
+
+//El proponente da offered (más near_amount) a cambio de los tokens requested del counterparty
+//The proposer gives offered (plus near_amount) in exchange for the requested tokens of the counterparty
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, BorshDeserialize, BorshSerialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Trade {
+    pub proposer_id: AccountId,
+    pub counterparty_id: AccountId,
+    pub offered: Vec<TradeToken>,
+    pub requested: Vec<TradeToken>,
+    pub near_amount: U128,
+    pub status: TradeStatus,
+    //Momento en que se aceptó, después de TRADE_SETTLE_TIMEOUT cualquiera puede llamar settle_trade
+    //Moment it was accepted, after TRADE_SETTLE_TIMEOUT anyone can call settle_trade
+    #[serde(default)]
+    pub settling_at: Option<U64>,
+    pub storage_deposit: U128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TradeWithId {
+    pub id: TradeId,
+    pub trade: Trade,
+}
+
+impl Trade {
+    //Tokens del trade con su owner, primero los ofrecidos y luego los requeridos
+    //Tokens of the trade with their owner, first the offered and then the requested ones
+    pub fn tokens(&self) -> impl Iterator<Item = (&TradeToken, &AccountId)> {
+        self.offered.iter().map(move |token| (token, &self.proposer_id))
+            .chain(self.requested.iter().map(move |token| (token, &self.counterparty_id)))
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    //Propone un trade, el deposito cubre near_amount y el storage, el exceso se devuelve
+    //Propose a trade, the deposit covers near_amount and the storage, the excess is refunded
+    #[payable]
+    pub fn propose_trade(
+        &mut self,
+        counterparty_id: AccountId,
+        offered: Vec<TradeToken>,
+        requested: Vec<TradeToken>,
+        near_amount: Option<U128>,
+    ) -> TradeId {
+        self.internal_assert_not_paused(PauseScope::Listing);
+        let proposer_id = env::predecessor_account_id();
+        let near_amount = near_amount.unwrap_or(U128(0));
+        require!(proposer_id != counterparty_id, "You can't trade with yourself");
+        require!(!requested.is_empty(), "A trade must request at least one token");
+        require!(!offered.is_empty() || near_amount.0 > 0, "A trade must offer tokens or NEAR");
+        require!(
+            offered.len() + requested.len() <= MAX_TRADE_TOKENS,
+            format!("A trade can move at most {} tokens", MAX_TRADE_TOKENS)
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        let trade_id = self.next_trade_id;
+        self.next_trade_id += 1;
+        let token_count = offered.len() + requested.len();
+        let mut trade = Trade {
+            proposer_id: proposer_id.clone(),
+            counterparty_id,
+            offered: offered.into_iter().map(|token| TradeToken { approval_id: None, ..token }).collect(),
+            requested: requested.into_iter().map(|token| TradeToken { approval_id: None, ..token }).collect(),
+            near_amount,
+            status: TradeStatus::Open,
+            settling_at: None,
+            storage_deposit: U128(0),
+        };
+        self.trades.insert(&trade_id, &trade);
+
+        //Se reservan 8 bytes por token para los approval IDs que llegan después
+        //8 bytes per token are reserved for the approval IDs that arrive later
+        let bytes = env::storage_usage() - initial_storage_usage + 8 * token_count as u64;
+        let cost = Balance::from(bytes) * STORAGE_PRICE_PER_BYTE;
+        let deposit = env::attached_deposit();
+        require!(
+            deposit >= near_amount.0 + cost,
+            format!("Attach at least {} yoctoNEAR (near_amount plus storage)", near_amount.0 + cost)
+        );
+        trade.storage_deposit = U128(cost);
+        self.trades.insert(&trade_id, &trade);
+        if deposit > near_amount.0 + cost {
+            Promise::new(proposer_id).transfer(deposit - near_amount.0 - cost);
+        }
+        env::log_str(&format!("Trade {} proposed", trade_id));
+        trade_id
+    }
+
+    //Cualquiera de las dos partes puede cancelar un trade abierto, el NEAR vuelve al proponente
+    //Either side can cancel an open trade, the NEAR goes back to the proposer
+    #[payable]
+    pub fn cancel_trade(&mut self, trade_id: TradeId) {
+        assert_one_yocto();
+        let trade = self.trades.get(&trade_id).expect("No trade found");
+        let caller_id = env::predecessor_account_id();
+        require!(
+            caller_id == trade.proposer_id || caller_id == trade.counterparty_id,
+            "Only the sides of the trade can cancel it"
+        );
+        require!(trade.status == TradeStatus::Open, "The trade is already settling");
+        self.trades.remove(&trade_id);
+        Promise::new(trade.proposer_id).transfer(trade.near_amount.0 + trade.storage_deposit.0);
+        env::log_str(&format!("Trade {} cancelled by {}", trade_id, caller_id));
+    }
+
+    //El counterparty acepta cuando ambos lados aprobaron sus tokens, primero todo pasa al escrow del marketplace
+    //The counterparty accepts once both sides approved their tokens, first everything goes to the marketplace escrow
+    #[payable]
+    pub fn accept_trade(&mut self, trade_id: TradeId) -> Promise {
+        assert_one_yocto();
+        self.internal_assert_not_paused(PauseScope::Purchases);
+        let mut trade = self.trades.get(&trade_id).expect("No trade found");
+        require!(env::predecessor_account_id() == trade.counterparty_id, "Only the counterparty can accept the trade");
+        require!(trade.status == TradeStatus::Open, "The trade is already settling");
+        require!(
+            trade.tokens().all(|(token, _)| token.approval_id.is_some()),
+            "Every token of the trade must be approved to the marketplace"
+        );
+        trade.status = TradeStatus::Settling;
+        trade.settling_at = Some(U64(env::block_timestamp()));
+        self.trades.insert(&trade_id, &trade);
+
+        let escrow = trade.tokens()
+            .map(|(token, _)| {
+                ext_contract::ext(token.nft_contract_id.clone())
+                    .with_attached_deposit(1)
+                    .with_static_gas(self.internal_gas_config(&token.nft_contract_id).nft_transfer())
+                    .nft_transfer(
+                        env::current_account_id(),
+                        token.token_id.clone(),
+                        token.approval_id,
+                        Some(format!("Escrow for Evie trade {}", trade_id)),
+                    )
+            })
+            .reduce(|transfers, transfer| transfers.and(transfer))
+            .unwrap();
+
+        escrow.then(ext_self::ext(env::current_account_id())
+            .with_static_gas(self.internal_trade_escrow_gas(&trade))
+            .resolve_trade_escrow(trade_id, false))
+    }
+
+    //Si el callback del escrow no se completó, cualquiera puede cerrar el trade pasado TRADE_SETTLE_TIMEOUT
+    //If the escrow callback didn't complete, anyone can close the trade after TRADE_SETTLE_TIMEOUT
+    //Se consulta con nft_token qué tokens tiene el marketplace y se resuelve como en accept_trade, liberando near_amount
+    //nft_token tells which tokens the marketplace holds and it is resolved as in accept_trade, releasing near_amount
+    pub fn settle_trade(&mut self, trade_id: TradeId) -> Promise {
+        let trade = self.trades.get(&trade_id).expect("No trade found");
+        require!(trade.status == TradeStatus::Settling, "The trade is not settling");
+        let settle_at = trade.settling_at.map(|settling_at| settling_at.0).unwrap_or(0) + TRADE_SETTLE_TIMEOUT;
+        require!(env::block_timestamp() >= settle_at, format!("The trade can be settled after {}", settle_at));
+
+        let tokens = trade.tokens()
+            .map(|(token, _)| {
+                ext_paras::ext(token.nft_contract_id.clone())
+                    .with_static_gas(self.internal_gas_config(&token.nft_contract_id).cross_contract_call())
+                    .nft_token(token.token_id.clone())
+            })
+            .reduce(|tokens, token| tokens.and(token))
+            .unwrap();
+
+        tokens.then(ext_self::ext(env::current_account_id())
+            .with_static_gas(self.internal_trade_escrow_gas(&trade))
+            .resolve_trade_escrow(trade_id, true))
+    }
+
+    //Si todo llegó al escrow se entrega a cada lado, si no, se devuelve lo que llegó a sus owners
+    //If everything reached the escrow it is delivered to each side, otherwise what arrived goes back to its owners
+    //Con check_owner los resultados son de nft_token (settle_trade) y cuenta quién es el owner actual
+    //With check_owner the results come from nft_token (settle_trade) and the current owner is what counts
+    //No hace panic: un trade que ya no está en Settling se ignora
+    //It doesn't panic: a trade that is no longer Settling is ignored
+    #[private]
+    pub fn resolve_trade_escrow(&mut self, trade_id: TradeId, check_owner: bool) -> bool {
+        let trade = match self.trades.get(&trade_id) {
+            Some(trade) if trade.status == TradeStatus::Settling => trade,
+            _ => {
+                env::log_str(&format!("Trade {} is not settling, nothing to resolve", trade_id));
+                return false;
+            }
+        };
+        self.trades.remove(&trade_id);
+        let escrowed: Vec<bool> = trade.tokens()
+            .enumerate()
+            .map(|(index, (token, owner_id))| {
+                let success = match env::promise_result(index as u64) {
+                    PromiseResult::Successful(_) if !check_owner => true,
+                    PromiseResult::Successful(value) => matches!(
+                        near_sdk::serde_json::from_slice::<Option<Token>>(&value),
+                        Ok(Some(token)) if token.owner_id == env::current_account_id()
+                    ),
+                    _ => false,
+                };
+                env::log_str(&format!(
+                    "Trade {}: escrow of {} {} from {} {}",
+                    trade_id, token.nft_contract_id, token.token_id, owner_id,
+                    if success { "succeeded" } else { "failed" }
+                ));
+                success
+            })
+            .collect();
+        let settled = escrowed.iter().all(|success| *success);
+
+        //Con éxito los ofrecidos van al counterparty y los requeridos al proponente, si no vuelven a su owner
+        //On success the offered go to the counterparty and the requested to the proposer, otherwise back to their owner
+        let transfers: Vec<EscrowTransfer> = trade.tokens()
+            .zip(escrowed.iter())
+            .filter(|(_, success)| **success)
+            .map(|((token, owner_id), _)| EscrowTransfer {
+                nft_contract_id: token.nft_contract_id.clone(),
+                token_id: token.token_id.clone(),
+                receiver_id: if !settled {
+                    owner_id.clone()
+                } else if *owner_id == trade.proposer_id {
+                    trade.counterparty_id.clone()
+                } else {
+                    trade.proposer_id.clone()
+                },
+            })
+            .collect();
+
+        if settled {
+            if trade.near_amount.0 > 0 {
+                Promise::new(trade.counterparty_id.clone()).transfer(trade.near_amount.0);
+            }
+            Promise::new(trade.proposer_id.clone()).transfer(trade.storage_deposit.0);
+        } else {
+            env::log_str(&format!("Trade {} failed, returning the escrowed tokens", trade_id));
+            Promise::new(trade.proposer_id.clone()).transfer(trade.near_amount.0 + trade.storage_deposit.0);
+        }
+
+        //Las entregas que fallen quedan en el escrow y se pueden reclamar con claim_escrowed_token
+        //Deliveries that fail stay in the escrow and can be claimed with claim_escrowed_token
+        self.internal_deliver_escrowed(transfers, format!("Evie trade {}", trade_id));
+        settled
+    }
+
+    pub fn get_trade(&self, trade_id: TradeId) -> Option<Trade> {
+        self.trades.get(&trade_id)
+    }
+
+    //Trades en los que participa una cuenta, como proponente o counterparty
+    //Trades an account takes part in, as proposer or counterparty
+    pub fn get_trades_for_account(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<TradeWithId> {
+        let start: u128 = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.trades.iter()
+            .filter(|(_, trade)| trade.proposer_id == account_id || trade.counterparty_id == account_id)
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .map(|(id, trade)| TradeWithId { id, trade })
+            .collect()
+    }
+}
+
+impl Contract {
+    //nft_on_approve con msg {"trade_id": ...}, guarda el approval ID del token en el trade
+    //nft_on_approve with msg {"trade_id": ...}, stores the approval ID of the token in the trade
+    pub fn internal_on_trade_approve(
+        &mut self,
+        trade_id: TradeId,
+        nft_contract_id: AccountId,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+    ) {
+        let mut trade = self.trades.get(&trade_id).expect("No trade found");
+        require!(trade.status == TradeStatus::Open, "The trade is already settling");
+        let tokens = if owner_id == trade.proposer_id {
+            &mut trade.offered
+        } else if owner_id == trade.counterparty_id {
+            &mut trade.requested
+        } else {
+            env::panic_str("Only the sides of the trade can approve tokens for it");
+        };
+        let token = tokens.iter_mut()
+            .find(|token| token.nft_contract_id == nft_contract_id && token.token_id == token_id)
+            .expect("The token is not part of this side of the trade");
+        token.approval_id = Some(approval_id);
+        self.trades.insert(&trade_id, &trade);
+        env::log_str(&format!("Trade {}: {} approved {} {}", trade_id, owner_id, nft_contract_id, token_id));
+    }
+
+    //resolve_trade_escrow más las entregas de todos los tokens del trade desde el escrow
+    //resolve_trade_escrow plus the deliveries of every token of the trade from the escrow
+    pub fn internal_trade_escrow_gas(&self, trade: &Trade) -> Gas {
+        GAS_FOR_RESOLVE_TRADE_ESCROW
+            + self.internal_escrow_delivery_gas(trade.tokens().map(|(token, _)| &token.nft_contract_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::PromiseResult;
+
+    use super::*;
+    use crate::testing::*;
+
+    fn token(token_id: &str) -> TradeToken {
+        TradeToken { nft_contract_id: nft(), token_id: token_id.to_string(), approval_id: None }
+    }
+
+    fn transfer(token_id: &str, receiver_id: &str) -> EscrowTransfer {
+        EscrowTransfer { nft_contract_id: nft(), token_id: token_id.to_string(), receiver_id: account(receiver_id) }
+    }
+
+    //alice ofrece el token 1 y 1 NEAR por el token 2 de bob, bob acepta en el momento 0
+    //alice offers the token 1 and 1 NEAR for the token 2 of bob, bob accepts at time 0
+    fn setup_settling_trade() -> Contract {
+        let mut contract = new_contract();
+        let alice = account("alice.near");
+        let bob = account("bob.near");
+        set_context(context(&alice).attached_deposit(2 * ONE_NEAR), vec![]);
+        contract.propose_trade(bob.clone(), vec![token("1")], vec![token("2")], Some(U128(ONE_NEAR)));
+        approve(&mut contract, &alice, "1", 1, r#"{"trade_id": 0}"#);
+        approve(&mut contract, &bob, "2", 2, r#"{"trade_id": 0}"#);
+        set_context(context(&bob).attached_deposit(1), vec![]);
+        contract.accept_trade(0);
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "The trade can be settled after")]
+    fn settle_trade_waits_for_the_timeout() {
+        let mut contract = setup_settling_trade();
+        set_context(context(&account("carol.near")).block_timestamp(TRADE_SETTLE_TIMEOUT - 1), vec![]);
+        contract.settle_trade(0);
+    }
+
+    #[test]
+    fn settle_trade_after_timeout_returns_escrowed_tokens() {
+        let mut contract = setup_settling_trade();
+        let storage_deposit = contract.trades.get(&0).unwrap().storage_deposit.0;
+        set_context(context(&account("carol.near")).block_timestamp(TRADE_SETTLE_TIMEOUT), vec![]);
+        contract.settle_trade(0);
+
+        //Solo el token 1 llegó al escrow, el trade falla y vuelve a alice con su NEAR
+        //Only the token 1 reached the escrow, the trade fails and it goes back to alice with her NEAR
+        set_callback_context(TRADE_SETTLE_TIMEOUT, vec![
+            token_owned_by(&marketplace(), None),
+            token_owned_by(&account("bob.near"), Some(2)),
+        ]);
+        assert!(!contract.resolve_trade_escrow(0, true));
+
+        assert!(contract.trades.get(&0).is_none());
+        assert_eq!(transferred_to(&account("alice.near")), ONE_NEAR + storage_deposit);
+        assert_eq!(transferred_to(&account("bob.near")), 0);
+        assert_eq!(nft_transfers(), vec![("1".to_string(), "alice.near".to_string())]);
+        assert_eq!(contract.get_escrowed_token(nft(), "1".to_string()), Some(transfer("1", "alice.near")));
+    }
+
+    #[test]
+    fn failed_delivery_can_be_claimed_by_anyone() {
+        let mut contract = setup_settling_trade();
+        set_callback_context(0, vec![PromiseResult::Successful(vec![]), PromiseResult::Successful(vec![])]);
+        assert!(contract.resolve_trade_escrow(0, false));
+        assert_eq!(
+            nft_transfers(),
+            vec![("1".to_string(), "bob.near".to_string()), ("2".to_string(), "alice.near".to_string())]
+        );
+
+        //La entrega a bob falla y queda en el escrow
+        //The delivery to bob fails and stays in the escrow
+        set_callback_context(0, vec![PromiseResult::Failed, PromiseResult::Successful(vec![])]);
+        assert!(!contract.resolve_escrow_deliveries(vec![transfer("1", "bob.near"), transfer("2", "alice.near")]));
+        assert_eq!(contract.get_escrowed_tokens(None, None), vec![transfer("1", "bob.near")]);
+
+        //carol la reintenta, el token sigue yendo a bob
+        //carol retries it, the token still goes to bob
+        set_context(&context(&account("carol.near")), vec![]);
+        contract.claim_escrowed_token(nft(), "1".to_string());
+        assert_eq!(nft_transfers(), vec![("1".to_string(), "bob.near".to_string())]);
+
+        set_callback_context(0, vec![PromiseResult::Successful(vec![])]);
+        assert!(contract.resolve_escrow_deliveries(vec![transfer("1", "bob.near")]));
+        assert!(contract.get_escrowed_tokens(None, None).is_empty());
+    }
+}